@@ -0,0 +1,150 @@
+//! Launching fireworks on the beats of the microphone or line-in, with
+//! `--audio-reactive`.
+//!
+//! Capturing audio needs the `audio-reactive` feature. The samples arrive on
+//! a thread of their own, which sends the volume of every beat over a
+//! channel for the show to poll.
+
+use anyhow::Result;
+use std::sync::mpsc::Receiver;
+
+/// Fraction of the threshold the volume has to fall back below before the
+/// next beat, so a single loud sound doesn't count as several.
+const REARM_LEVEL: f64 = 0.7;
+
+/// Finds the beats in the peaks of consecutive chunks of samples.
+///
+/// Kept apart from capturing the samples, so it builds and is tested without
+/// the `audio-reactive` feature.
+#[cfg_attr(not(feature = "audio-reactive"), allow(dead_code))]
+struct BeatDetector {
+    threshold: f64,
+    /// Whether the volume was quiet since the last beat.
+    armed: bool,
+}
+
+#[cfg_attr(not(feature = "audio-reactive"), allow(dead_code))]
+impl BeatDetector {
+    fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            armed: true,
+        }
+    }
+
+    /// The volume of the beat in a chunk of samples with the `peak` volume
+    /// from 0 to 1, if it is one.
+    fn hear(&mut self, peak: f64) -> Option<f64> {
+        if self.armed && peak >= self.threshold {
+            self.armed = false;
+            return Some(peak);
+        }
+        if peak < self.threshold * REARM_LEVEL {
+            self.armed = true;
+        }
+        None
+    }
+}
+
+/// Starts listening to the default input device, returning the volumes of
+/// the beats it hears, from the `threshold` up to 1.
+///
+/// A beat is a peak in the samples reaching the `threshold` after they were
+/// quiet for a while.
+#[cfg(feature = "audio-reactive")]
+pub fn listen(threshold: f64) -> Result<Receiver<f64>> {
+    use std::sync::mpsc;
+
+    let (beats, receiver) = mpsc::channel();
+    let (started, start) = mpsc::channel();
+    // Streams can't move between threads on every platform, so the thread
+    // keeps its stream until the program ends.
+    std::thread::spawn(move || match capture(threshold, beats) {
+        Ok(_stream) => {
+            let _ = started.send(Ok(()));
+            loop {
+                std::thread::park();
+            }
+        }
+        Err(error) => {
+            let _ = started.send(Err(error));
+        }
+    });
+    start.recv()??;
+    Ok(receiver)
+}
+
+/// Audio input isn't available without the `audio-reactive` feature.
+#[cfg(not(feature = "audio-reactive"))]
+pub fn listen(_threshold: f64) -> Result<Receiver<f64>> {
+    anyhow::bail!("built without the `audio-reactive` feature")
+}
+
+/// Plays a stream of the default input device, sending the volume of every
+/// beat to `beats`.
+#[cfg(feature = "audio-reactive")]
+fn capture(threshold: f64, beats: std::sync::mpsc::Sender<f64>) -> Result<cpal::Stream> {
+    use anyhow::{bail, Context};
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::SampleFormat;
+
+    let device = cpal::default_host()
+        .default_input_device()
+        .context("no audio input device found")?;
+    let config = device.default_input_config()?;
+    let stream = match config.sample_format() {
+        SampleFormat::F32 => stream::<f32>(&device, config.config(), threshold, beats)?,
+        SampleFormat::I16 => stream::<i16>(&device, config.config(), threshold, beats)?,
+        SampleFormat::U16 => stream::<u16>(&device, config.config(), threshold, beats)?,
+        format => bail!("unsupported audio sample format {format}"),
+    };
+    stream.play()?;
+    Ok(stream)
+}
+
+#[cfg(feature = "audio-reactive")]
+fn stream<T>(
+    device: &cpal::Device,
+    config: cpal::StreamConfig,
+    threshold: f64,
+    beats: std::sync::mpsc::Sender<f64>,
+) -> Result<cpal::Stream>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    use cpal::traits::DeviceTrait;
+
+    let mut detector = BeatDetector::new(threshold);
+    let stream = device.build_input_stream(
+        config,
+        move |samples: &[T], _: &cpal::InputCallbackInfo| {
+            let peak = samples
+                .iter()
+                .map(|sample| sample.to_sample::<f32>().abs() as f64)
+                .fold(0.0, f64::max)
+                .min(1.0);
+            if let Some(volume) = detector.hear(peak) {
+                let _ = beats.send(volume);
+            }
+        },
+        // Dropouts only cost a few beats.
+        |_| {},
+        None,
+    )?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loud_sounds_are_single_beats() {
+        let mut detector = BeatDetector::new(0.5);
+        let peaks = [0.2, 0.6, 0.9, 0.4, 0.8, 0.3, 0.5];
+        let beats: Vec<_> = peaks.iter().map(|&peak| detector.hear(peak)).collect();
+        // Only quiet below 0.35 rearms it.
+        assert_eq!(beats, [None, Some(0.6), None, None, None, None, Some(0.5)]);
+    }
+}
@@ -0,0 +1,45 @@
+//! Headless benchmark of the simulation, free of any terminal output.
+
+use anyhow::Result;
+use pixel_loop::canvas::RenderableCanvas;
+use pixel_loop::rand::SeedableRng;
+use pixel_loop::rand_xoshiro::Xoshiro256PlusPlus;
+use std::time::Instant;
+
+use crate::canvas::pixel_size;
+use crate::{draw_scene, tick, NoopCanvas, SimParams, State};
+
+/// Runs `frames` simulation steps at full launch density on a canvas of
+/// `columns` x `rows` terminal cells, drawing each one into memory, and
+/// prints how long it took.
+pub fn run(mut state: State, seed: u64, frames: usize, columns: u16, rows: u16) -> Result<()> {
+    let (width, height) = pixel_size(columns, rows);
+    let mut canvas = NoopCanvas::new(width, height);
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+    let params = SimParams {
+        width,
+        height,
+        spawn_chance: 1.0,
+        ..state.sim_params()
+    };
+
+    let mut peak_particles = 0;
+    let start = Instant::now();
+    for _ in 0..frames {
+        tick(&mut state, &mut rng, &params);
+        draw_scene(&mut state, &mut canvas);
+        canvas.render()?;
+        peak_particles = peak_particles.max(state.stats().particles);
+    }
+    let elapsed = start.elapsed();
+
+    println!("frames:         {frames} on {width}x{height} pixels");
+    println!("total time:     {:.3}s", elapsed.as_secs_f64());
+    println!(
+        "frame time:     {:.3}ms",
+        elapsed.as_secs_f64() * 1000.0 / frames as f64
+    );
+    println!("peak particles: {peak_particles}");
+    println!("pooled:         {}", state.pool.available());
+    Ok(())
+}
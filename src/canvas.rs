@@ -0,0 +1,474 @@
+//! Canvases the show is rendered on.
+
+use anyhow::Result;
+use crossterm::cursor::MoveTo;
+use crossterm::execute;
+use crossterm::style::ResetColor;
+use crossterm::terminal::{self, ClearType};
+use pixel_loop::canvas::{Canvas, CrosstermCanvas, RenderableCanvas};
+use pixel_loop::color::Color;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::color::{self, Blend};
+use crate::sim::NoopCanvas;
+
+/// Frames written per second by the [AnsiCanvas] and [RedrawCanvas].
+const ANSI_FPS: u32 = 60;
+
+/// Size in pixels of a canvas covering `columns` x `rows` terminal cells,
+/// never smaller than a single cell.
+///
+/// Every cell shows an upper and a lower pixel, so the height is always even
+/// and the bottom row of cells is filled completely. It also stays within the
+/// `u16` pixel height of a [CrosstermCanvas].
+pub fn pixel_size(columns: u16, rows: u16) -> (u32, u32) {
+    (
+        columns.max(1) as u32,
+        rows.clamp(1, u16::MAX / 2) as u32 * 2,
+    )
+}
+
+/// A canvas covering a grid of terminal cells, two pixels per cell.
+pub trait ShowCanvas: RenderableCanvas + Sized {
+    /// Creates an empty canvas for `columns` x `rows` cells, never smaller
+    /// than a single cell.
+    fn for_cells(columns: u16, rows: u16) -> Self;
+
+    /// Cleans up the output once the show is over.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ShowCanvas for CrosstermCanvas {
+    fn for_cells(columns: u16, rows: u16) -> Self {
+        let (width, height) = pixel_size(columns, rows);
+        let mut canvas = CrosstermCanvas::new(width as u16, height as u16);
+        canvas.set_refresh_limit(120);
+        canvas
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        clear_terminal()
+    }
+}
+
+/// Resets the colors and clears the whole terminal.
+fn clear_terminal() -> Result<()> {
+    execute!(
+        std::io::stdout(),
+        ResetColor,
+        terminal::Clear(ClearType::All),
+        MoveTo(0, 0)
+    )?;
+    Ok(())
+}
+
+/// Writes every frame to stdout as ANSI colored text, so the show can be
+/// piped into a file or another program.
+pub struct AnsiCanvas {
+    width: u32,
+    height: u32,
+    buffer: Vec<Color>,
+    last_frame: Instant,
+}
+
+impl ShowCanvas for AnsiCanvas {
+    fn for_cells(columns: u16, rows: u16) -> Self {
+        let (width, height) = pixel_size(columns, rows);
+        Self {
+            width,
+            height,
+            buffer: vec![Color::from_rgb(0, 0, 0); (width * height) as usize],
+            last_frame: Instant::now(),
+        }
+    }
+}
+
+impl Canvas for AnsiCanvas {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn get_range(&self, range: Range<usize>) -> &[Color] {
+        &self.buffer[range]
+    }
+
+    fn set_range(&mut self, range: Range<usize>, colors: &[Color]) {
+        self.buffer[range].copy_from_slice(colors);
+    }
+}
+
+impl RenderableCanvas for AnsiCanvas {
+    fn render(&mut self) -> Result<()> {
+        // Nothing waits for the output, so the frame rate is limited here.
+        wait_for_frame(&mut self.last_frame);
+
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(frame_to_ansi(&self.buffer, self.width, self.height).as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    fn physical_pos_to_canvas_pos(&self, _x: f64, _y: f64) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn resize_surface(&mut self, width: u32, height: u32) {
+        self.resize(width, height);
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.buffer = vec![Color::from_rgb(0, 0, 0); (width * height) as usize];
+    }
+}
+
+/// Sleeps until a frame at [ANSI_FPS] after the `last_frame` is due, and
+/// sets it to now.
+fn wait_for_frame(last_frame: &mut Instant) {
+    let frame_time = Duration::from_secs(1) / ANSI_FPS;
+    let elapsed = last_frame.elapsed();
+    if elapsed < frame_time {
+        std::thread::sleep(frame_time - elapsed);
+    }
+    *last_frame = Instant::now();
+}
+
+/// Redraws every cell of the terminal on each frame, unlike the
+/// [CrosstermCanvas] which only writes the cells that changed since the last
+/// one. Only meant for comparing the two with `--no-diff`.
+pub struct RedrawCanvas(AnsiCanvas);
+
+impl ShowCanvas for RedrawCanvas {
+    fn for_cells(columns: u16, rows: u16) -> Self {
+        Self(AnsiCanvas::for_cells(columns, rows))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        clear_terminal()
+    }
+}
+
+impl Canvas for RedrawCanvas {
+    fn width(&self) -> u32 {
+        self.0.width
+    }
+
+    fn height(&self) -> u32 {
+        self.0.height
+    }
+
+    fn get_range(&self, range: Range<usize>) -> &[Color] {
+        self.0.get_range(range)
+    }
+
+    fn set_range(&mut self, range: Range<usize>, colors: &[Color]) {
+        self.0.set_range(range, colors);
+    }
+}
+
+impl RenderableCanvas for RedrawCanvas {
+    fn render(&mut self) -> Result<()> {
+        let canvas = &mut self.0;
+        wait_for_frame(&mut canvas.last_frame);
+
+        // Every line is placed on its row, as a newline after the last one
+        // would scroll the terminal.
+        let frame = frame_to_ansi(&canvas.buffer, canvas.width, canvas.height);
+        let mut text = String::with_capacity(frame.len());
+        for (row, line) in frame.lines().enumerate() {
+            let _ = write!(text, "\x1b[{};1H{line}", row + 1);
+        }
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(text.as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    fn physical_pos_to_canvas_pos(&self, x: f64, y: f64) -> Option<(u32, u32)> {
+        self.0.physical_pos_to_canvas_pos(x, y)
+    }
+
+    fn resize_surface(&mut self, width: u32, height: u32) {
+        self.0.resize_surface(width, height);
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.0.resize(width, height);
+    }
+}
+
+impl ShowCanvas for NoopCanvas {
+    fn for_cells(columns: u16, rows: u16) -> Self {
+        let (width, height) = pixel_size(columns, rows);
+        Self::new(width, height)
+    }
+}
+
+/// Converts `width` x `height` pixels to lines of `▀` glyphs, each showing
+/// two pixels through its foreground and background color.
+///
+/// Colors are reset at the end of every line, so the text can be shown with
+/// `cat` or `less -R`.
+pub fn frame_to_ansi(pixels: &[Color], width: u32, height: u32) -> String {
+    let black = Color::from_rgb(0, 0, 0);
+    let (width, height) = (width as usize, height as usize);
+    let mut text = String::new();
+    for y in (0..height).step_by(2) {
+        let mut previous = None;
+        for x in 0..width {
+            let upper = pixels[y * width + x];
+            let lower = if y + 1 < height {
+                pixels[(y + 1) * width + x]
+            } else {
+                black
+            };
+            // Runs of equal cells only need their colors set once.
+            if previous != Some((upper, lower)) {
+                let _ = write!(
+                    text,
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                    upper.r, upper.g, upper.b, lower.r, lower.g, lower.b
+                );
+                previous = Some((upper, lower));
+            }
+            text.push('▀');
+        }
+        text.push_str("\x1b[0m\n");
+    }
+    text
+}
+
+/// Fills a `width` x `height` rectangle at `x`, `y` with `color`, clipped to
+/// the canvas.
+///
+/// Unlike `Canvas::filled_rect`, this reaches the last row and column of the
+/// canvas, so sparks at the bottom and right edge aren't cut off.
+pub fn fill_rect<C: Canvas>(
+    canvas: &mut C,
+    x: i64,
+    y: i64,
+    width: u32,
+    height: u32,
+    color: &Color,
+) {
+    let (canvas_width, canvas_height) = (canvas.width() as i64, canvas.height() as i64);
+    let (left, top) = (x.max(0), y.max(0));
+    let right = (x + width as i64).min(canvas_width);
+    let bottom = (y + height as i64).min(canvas_height);
+    if left >= right || top >= bottom {
+        return;
+    }
+    let row = vec![*color; (right - left) as usize];
+    for y in top..bottom {
+        let start = (y * canvas_width + left) as usize;
+        canvas.set_range(start..start + row.len(), &row);
+    }
+}
+
+/// Draws the outline of a circle around `center` with the midpoint circle
+/// algorithm, leaving out the pixels beyond the canvas.
+pub fn draw_circle_outline<C: Canvas>(
+    canvas: &mut C,
+    center: (i64, i64),
+    radius: i64,
+    color: &Color,
+) {
+    let (cx, cy) = center;
+    let mut plot = |x: i64, y: i64| {
+        if canvas.maybe_get(x, y).is_some() {
+            canvas.set(x as u32, y as u32, color);
+        }
+    };
+    let (mut x, mut y) = (radius, 0);
+    let mut error = 1 - radius;
+    while x >= y {
+        // Every step sets one pixel in each of the eight octants.
+        for (dx, dy) in [(x, y), (y, x)] {
+            plot(cx + dx, cy + dy);
+            plot(cx - dx, cy + dy);
+            plot(cx + dx, cy - dy);
+            plot(cx - dx, cy - dy);
+        }
+        y += 1;
+        if error < 0 {
+            error += 2 * y + 1;
+        } else {
+            x -= 1;
+            error += 2 * (y - x) + 1;
+        }
+    }
+}
+
+/// Pixels on the line from `from` to `to` with Bresenham's algorithm, both
+/// ends included and in order.
+pub fn line_pixels(from: (i64, i64), to: (i64, i64)) -> Vec<(i64, i64)> {
+    let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+    let step = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+    let (mut x, mut y) = from;
+    let mut error = dx + dy;
+    let mut pixels = vec![];
+    loop {
+        pixels.push((x, y));
+        if (x, y) == to {
+            return pixels;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step.0;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step.1;
+        }
+    }
+}
+
+/// Mirrors the lightness of every pixel on the canvas, see
+/// [color::mirror_lightness].
+pub fn mirror_lightness<C: Canvas>(canvas: &mut C) {
+    let width = canvas.width() as usize;
+    for y in 0..canvas.height() as usize {
+        let range = y * width..(y + 1) * width;
+        let row: Vec<Color> = canvas
+            .get_range(range.clone())
+            .iter()
+            .map(color::mirror_lightness)
+            .collect();
+        canvas.set_range(range, &row);
+    }
+}
+
+/// Scales the brightness of every pixel on the canvas by `brightness`.
+pub fn darken<C: Canvas>(canvas: &mut C, brightness: f64) {
+    fade(canvas, &Sky::plain(Color::from_rgb(0, 0, 0)), brightness);
+}
+
+/// Colors of the sky, blending from the top row into the horizon at the
+/// bottom one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sky {
+    pub top: Color,
+    /// Color of the bottom row, the same as the top one for a plain sky.
+    pub horizon: Color,
+}
+
+impl Sky {
+    /// A sky of a single `color`.
+    pub fn plain(color: Color) -> Self {
+        Self {
+            top: color,
+            horizon: color,
+        }
+    }
+
+    /// Color of the sky in row `y` of a canvas `height` pixels high.
+    pub fn at(&self, y: i64, height: u32) -> Color {
+        if self.top == self.horizon {
+            return self.top;
+        }
+        let depth = y as f64 / height.saturating_sub(1).max(1) as f64;
+        Color::blend(&self.top, &self.horizon, depth)
+    }
+
+    /// The sky with both colors mirrored, see [color::mirror_lightness].
+    pub fn mirror_lightness(&self) -> Self {
+        Self {
+            top: color::mirror_lightness(&self.top),
+            horizon: color::mirror_lightness(&self.horizon),
+        }
+    }
+}
+
+/// Paints the whole canvas with the `sky`.
+pub fn fill_sky<C: Canvas>(canvas: &mut C, sky: &Sky) {
+    let (width, height) = (canvas.width(), canvas.height());
+    for y in 0..height {
+        fill_rect(canvas, 0, y as i64, width, 1, &sky.at(y as i64, height));
+    }
+}
+
+/// Blends every pixel on the canvas into the `sky` behind it, keeping
+/// `brightness` of its own color.
+///
+/// Channels are rounded towards the sky, so fading over and over reaches it
+/// instead of getting stuck a few shades away.
+pub fn fade<C: Canvas>(canvas: &mut C, sky: &Sky, brightness: f64) {
+    let brightness = brightness.clamp(0.0, 1.0);
+    let mix = |background: u8, color: u8| {
+        let difference = (color as f64 - background as f64) * brightness;
+        (background as f64 + difference.trunc()) as u8
+    };
+    let (width, height) = (canvas.width() as usize, canvas.height());
+    for y in 0..height as usize {
+        let background = sky.at(y as i64, height);
+        let range = y * width..(y + 1) * width;
+        let row: Vec<Color> = canvas
+            .get_range(range.clone())
+            .iter()
+            .map(|color| {
+                Color::from_rgb(
+                    mix(background.r, color.r),
+                    mix(background.g, color.g),
+                    mix(background.b, color.b),
+                )
+            })
+            .collect();
+        canvas.set_range(range, &row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_hold_two_pixel_rows() {
+        for rows in [1, 2, 23, 24, 49] {
+            assert_eq!(pixel_size(80, rows), (80, rows as u32 * 2));
+        }
+        assert_eq!(pixel_size(0, 0), (1, 2));
+        assert_eq!(pixel_size(80, u16::MAX), (80, u16::MAX as u32 - 1));
+    }
+
+    #[test]
+    fn fill_rect_reaches_the_bottom_row() {
+        let red = Color::from_rgb(255, 0, 0);
+        for rows in [1, 2, 23, 24] {
+            let mut canvas = NoopCanvas::for_cells(10, rows);
+            let (width, height) = (canvas.width(), canvas.height());
+            fill_rect(&mut canvas, 0, 0, width, height, &red);
+            assert!(canvas.pixels().iter().all(|&pixel| pixel == red));
+            // A spark in the lowest pixel row shows in the lowest cell row.
+            let mut canvas = NoopCanvas::for_cells(10, rows);
+            fill_rect(&mut canvas, 0, height as i64 - 1, 1, 1, &red);
+            let text = frame_to_ansi(canvas.pixels(), width, height);
+            assert_eq!(text.lines().count(), rows as usize);
+            assert!(text.lines().last().unwrap().contains("48;2;255;0;0"));
+        }
+    }
+
+    #[test]
+    fn sky_blends_into_the_horizon() {
+        let sky = Sky {
+            top: Color::from_rgb(0, 0, 0),
+            horizon: Color::from_rgb(200, 100, 0),
+        };
+        let mut canvas = NoopCanvas::new(4, 11);
+        fill_sky(&mut canvas, &sky);
+        let row = |y: u32| canvas.pixels()[(y * 4) as usize];
+        assert_eq!(row(0), sky.top);
+        assert_eq!(row(10), sky.horizon);
+        assert_eq!(row(5), Color::from_rgb(100, 50, 0));
+    }
+}
@@ -0,0 +1,75 @@
+//! Colors of the metal salts real fireworks are colored with.
+
+use pixel_loop::color::Color;
+use pixel_loop::rand::Rng;
+
+/// A metal whose salts give a firework its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Element {
+    Strontium,
+    Calcium,
+    Sodium,
+    Barium,
+    Copper,
+    Potassium,
+}
+
+/// Every element with how often it is picked. Reds and greens are the most
+/// common colors in real shows, while blues are hard to make.
+const ELEMENTS: [(Element, u32); 6] = [
+    (Element::Strontium, 4),
+    (Element::Calcium, 2),
+    (Element::Sodium, 3),
+    (Element::Barium, 3),
+    (Element::Copper, 1),
+    (Element::Potassium, 1),
+];
+
+/// Names of all elements, as accepted by [Element::from_name].
+pub const ELEMENT_NAMES: &[&str] = &[
+    "strontium",
+    "calcium",
+    "sodium",
+    "barium",
+    "copper",
+    "potassium",
+];
+
+impl Element {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "strontium" => Some(Element::Strontium),
+            "calcium" => Some(Element::Calcium),
+            "sodium" => Some(Element::Sodium),
+            "barium" => Some(Element::Barium),
+            "copper" => Some(Element::Copper),
+            "potassium" => Some(Element::Potassium),
+            _ => None,
+        }
+    }
+
+    /// Color of the flame the element burns with.
+    pub fn color(self) -> Color {
+        match self {
+            Element::Strontium => Color::from_rgb(255, 30, 40),
+            Element::Calcium => Color::from_rgb(255, 120, 20),
+            Element::Sodium => Color::from_rgb(255, 200, 50),
+            Element::Barium => Color::from_rgb(90, 255, 60),
+            Element::Copper => Color::from_rgb(40, 110, 255),
+            Element::Potassium => Color::from_rgb(180, 80, 255),
+        }
+    }
+}
+
+/// Color of a randomly picked element, weighted by how common it is.
+pub fn chemistry_color<R: Rng + ?Sized>(rng: &mut R) -> Color {
+    let total: u32 = ELEMENTS.iter().map(|&(_, weight)| weight).sum();
+    let mut pick = rng.gen_range(0..total);
+    for (element, weight) in ELEMENTS {
+        if pick < weight {
+            return element.color();
+        }
+        pick -= weight;
+    }
+    unreachable!("pick is below the total weight")
+}
@@ -0,0 +1,546 @@
+use anyhow::{anyhow, bail, Context, Result};
+use pixel_loop::color::Color;
+use std::fmt::Display;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::chemistry::{Element, ELEMENT_NAMES};
+use crate::color::FromHex;
+use crate::config::Config;
+use crate::palette::{self, PALETTE_NAMES};
+use crate::preset::{self, FireworkPreset};
+use crate::shape::Shape;
+use crate::sim::{
+    Boundary, FadeCurve, FireworkKind, BOUNDARY_NAMES, FADE_CURVE_NAMES, FIREWORK_KIND_NAMES,
+};
+use crate::theme::{self, THEME_NAMES};
+use crate::{SimParams, MAX_WIND};
+
+/// Longest supported spark trail, keeps a typo from allocating gigabytes.
+const MAX_TRAIL: usize = 50;
+
+const USAGE: &str = "\
+Usage: terminal_fireworks [OPTIONS]
+
+Options:
+  --seed <u64>                      Seed for the random number generator (replays a show)
+  --density <f64>                   Chance per frame to launch a firework, 0.0-1.0 [default: 0.1]
+  --audio-reactive                  Launch on the beats of the microphone or line-in instead of randomly, needs the audio-reactive feature
+  --audio-threshold <f64>           Volume at which a sound counts as a beat, 0.01-1 [default: 0.3]
+  --steady                          Launch at evenly spaced times instead of randomly, at the same average rate
+  --idle-secs <f64>                 Seconds without input after which the show gets livelier, 0-86400
+  --spread <f64>                    How far launches spread from the center, 0 is the center only, 1 the full width [default: 1]
+  --wind <f64>                      Horizontal push on sparks, -0.1-0.1, positive blows right [default: 0]
+  --spark-size <u32>                Width and height of burst sparks in pixels, at least 1 [default: 1]
+  --vary-size                       Make a few burst sparks bigger and longer lasting
+  --rocket-width <u32>              Width of rising rockets in pixels, at least 1 [default: 1]
+  --rocket-height <u32>             Height of rising rockets in pixels, at least 1 [default: 3]
+  --spark-density <f64>             Sparks added to a burst per 1000 pixels of canvas, 0-20 [default: 2.5]
+  --min-spark-frames <u32>          Fewest steps every spark lasts however fast it fades, 0-600, 0 disables it [default: 5]
+  --max-age <u32>                   Steps after which a firework is removed however long its sparks last, at least 1 [default: 600]
+  --trail <usize>                   Length of the streak behind each spark, 0-50, 0 disables it [default: 0]
+  --gravity <f64>                   Downward acceleration per frame, 0.0-0.5 [default: 0.02]
+  --gravity-jitter <f64>            Fraction by which gravity and drag of each spark randomly vary, 0-1 [default: 0]
+  --spread-angle <deg>              Spray sphere and willow sparks into a cone this wide around straight up, 1-360
+  --rise <f64>                      How strongly sphere and willow sparks are thrown upwards, 0 is evenly all around, 2 a fountain [default: 1]
+  --drag <f64>                      Fraction of their speed sparks lose per frame, 0.0-0.5 [default: 0.02]
+  --attractors                      Place points pulling sparks towards them with the right mouse button
+  --boundary <name>                 What happens to sparks leaving to the side: wrap, clamp, cull [default: cull]
+  --ground                          Let sparks bounce off and settle on the bottom edge
+  --kinds <list>                    Launch only these comma separated kinds: sphere, ring, willow, palm [default: all]
+  --multistage                      Let some sparks burst a second time
+  --crackle                         Let dying sparks flicker
+  --twinkle                         Let sparks leave short lived twinkles behind
+  --mortar                          Let launches start with the mortar flashing at the base before the rocket rises
+  --mortar-frames <u32>             Steps the mortar flashes for with --mortar, 1-120 [default: 15]
+  --dud-rate <f64>                  Chance of a rocket to fizzle out or burst into only a few sparks, 0-1 [default: 0]
+  --tinted-rockets                  Tint rising rockets with a light version of their burst color instead of white
+  --launch-trail                    Let rising rockets leave a trail of warm sparks
+  --cooling <f64>                   Degrees the hue of sparks turns by as they fade, -360-360 [default: 0]
+  --mirror                          Mirror every firework spark for spark across the center of the sky
+  --shape <text>                    Let bursts spell up to 8 letters and digits, or draw a heart with `heart`
+  --glow                            Let bursts briefly light up the sky around them, slower to draw
+  --shockwave                       Let bursts send out a thin expanding ring
+  --stars <n>                       Faint twinkling stars in the sky, 0-10000 [default: 0]
+  --comets                          Let comets with long tails cross the sky now and then
+  --comet-rate <f64>                Comets per minute on average with --comets, 0.1-60 [default: 2]
+  --bell                            Ring the terminal bell when a rocket bursts
+  --background <hex>                Color of the sky as #rrggbb or #rgb [default: the terminal's background, or #000000]
+  --horizon <hex>                   Color the sky blends into from the background towards the bottom, as #rrggbb or #rgb
+  --invert                          Draw dark sparks on a light sky for light terminals, white unless the terminal's is light
+  --additive                        Add up the light of overlapping sparks, slower to draw
+  --long-exposure <f64>             Keep the light of earlier frames, this share of it per step, 0.0-1.0, e.g. 0.97
+  --fade-curve <name>               How sparks dim as they burn out: linear, easeout, quadratic, solid [default: linear]
+  --motion-blur                     Draw fast sparks as streaks along their path
+  --blur-speed <f64>                Pixels per step above which sparks streak with --motion-blur, 0-10 [default: 1]
+  --smooth                          Antialias sparks across neighboring pixels for smoother slow motion, at some cost
+  --no-fade                         Keep sparks at full brightness until they vanish, same as --fade-curve solid
+  --palette <name>                  Pick launch colors from a palette: classic, patriotic, pastel, fire, cool, gold, neon
+  --palette-file <path>             Pick launch colors from a file with one #rrggbb color per line
+  --colors <n>                      Pick launch colors from n random colors chosen for the whole show, 1-256
+  --theme <name>                    Preset of background, palette and physics, other flags override it: night, dawn, space
+  --rainbow                         Step launch colors through the color wheel instead of picking them randomly
+  --rainbow-step <f64>              Degrees the hue turns by per launch with --rainbow, -180-180 [default: 30]
+  --hue-drift <f64>                 Degrees per second the hues of random colors and palettes slowly turn by, -60-60 [default: 0]
+  --chemistry                       Color launches like the metal salts of real fireworks
+  --element <name>                  Color every launch with one metal salt: strontium, calcium, sodium, barium, copper, potassium
+  --flash-core                      Let bursts flash white at their center like flash powder
+  --depth <f64>                     Desaturate and dim the fastest sparks of bursts for depth, 0.0-1.0 [default: 0]
+  --two-tone                        Give the inner sparks of every burst a second color
+  --max-fireworks <usize>           Most fireworks in flight at once, counting those of --mirror, at least 1 [default: 100]
+  --particles-per-firework <usize>  Most particles each firework keeps, over all its stages, the dimmest making room for new ones, at least 1
+  --script <path>                   Launch the fireworks listed in a script file instead of random ones
+  --loop-script                     Start the script over once all its launches are done
+  --fps <u32>                       Updates per second, 1-240, the show runs equally fast at any rate [default: 60]
+  --duration <secs>                 Fade out and quit after running this long, 0-86400
+  --status-bar                      Show pause, density, wind and frame rate in the bottom terminal row
+  --record <path.gif>               Record the show into an animated GIF
+  --record-frames <usize>           Most frames to record, at least 1 [default: 600]
+  --record-replay <path>            Write the input and launches of the show to a replay file when it ends
+  --play-replay <path>              Play back a replay file instead of reacting to input and launching randomly
+  --stats-json <path>               Write a JSON summary of the run to a file when it ends
+  --ascii                           Write the frames to stdout as ANSI colored text, for piping
+  --no-diff                         Redraw the whole terminal every frame instead of only the changed cells, for comparison
+  --bench <frames>                  Time the given number of frames at full density without drawing to the terminal
+  -h, --help                        Print this help
+";
+
+/// Command line arguments of the firework show.
+pub struct Args {
+    pub seed: Option<u64>,
+    pub density: f64,
+    pub audio_reactive: bool,
+    pub audio_threshold: f64,
+    pub steady: bool,
+    pub idle_secs: Option<f64>,
+    pub spread: f64,
+    pub wind: f64,
+    pub spark_size: u32,
+    pub spark_density: f64,
+    pub vary_size: bool,
+    pub rocket_width: u32,
+    pub rocket_height: u32,
+    pub min_spark_frames: u32,
+    pub max_age: u32,
+    pub trail: usize,
+    pub gravity: f64,
+    pub gravity_jitter: f64,
+    pub spread_angle: Option<f64>,
+    pub rise: f64,
+    pub drag: f64,
+    pub attractors: bool,
+    pub boundary: Boundary,
+    pub ground: bool,
+    /// Kinds of the random launches, all of them if empty.
+    pub kinds: Vec<FireworkKind>,
+    pub multistage: bool,
+    pub crackle: bool,
+    pub cooling: f64,
+    pub twinkle: bool,
+    pub mortar: bool,
+    pub mortar_frames: u32,
+    pub dud_rate: f64,
+    /// Fireworks launched by the number keys, only set by the config file.
+    pub presets: Vec<FireworkPreset>,
+    pub tinted_rockets: bool,
+    pub launch_trail: bool,
+    pub mirror: bool,
+    pub shape: Option<Shape>,
+    pub glow: bool,
+    pub shockwave: bool,
+    pub stars: usize,
+    pub comets: bool,
+    pub comet_rate: f64,
+    pub bell: bool,
+    /// Color of the sky, the terminal's background without one.
+    pub background: Option<Color>,
+    /// Color of the bottom of the sky, blending into the background above.
+    pub horizon: Option<Color>,
+    pub invert: bool,
+    pub additive: bool,
+    pub long_exposure: Option<f64>,
+    pub fade_curve: FadeCurve,
+    pub motion_blur: bool,
+    pub blur_speed: f64,
+    pub smooth: bool,
+    pub palette: Option<Vec<Color>>,
+    pub colors: Option<usize>,
+    pub rainbow: bool,
+    pub rainbow_step: f64,
+    pub hue_drift: f64,
+    pub chemistry: bool,
+    pub element: Option<Element>,
+    pub flash_core: bool,
+    pub depth: f64,
+    pub two_tone: bool,
+    pub max_fireworks: usize,
+    pub particles_per_firework: Option<usize>,
+    pub script: Option<PathBuf>,
+    pub loop_script: bool,
+    pub fps: u32,
+    pub duration: Option<f64>,
+    pub status_bar: bool,
+    pub record: Option<PathBuf>,
+    pub record_frames: usize,
+    pub record_replay: Option<PathBuf>,
+    pub play_replay: Option<PathBuf>,
+    pub stats_json: Option<PathBuf>,
+    pub ascii: bool,
+    pub no_diff: bool,
+    pub bench: Option<usize>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        let physics = SimParams::default();
+        Self {
+            seed: None,
+            density: physics.spawn_chance,
+            audio_reactive: false,
+            audio_threshold: 0.3,
+            steady: false,
+            idle_secs: None,
+            spread: 1.0,
+            wind: physics.wind,
+            spark_size: 1,
+            spark_density: 2.5,
+            vary_size: false,
+            rocket_width: 1,
+            rocket_height: 3,
+            min_spark_frames: 5,
+            max_age: 600,
+            trail: 0,
+            gravity: physics.gravity,
+            gravity_jitter: 0.0,
+            spread_angle: None,
+            rise: 1.0,
+            drag: physics.drag,
+            attractors: false,
+            boundary: physics.boundary,
+            ground: physics.ground,
+            kinds: vec![],
+            multistage: false,
+            crackle: false,
+            cooling: 0.0,
+            twinkle: false,
+            mortar: false,
+            mortar_frames: 15,
+            dud_rate: 0.0,
+            presets: preset::defaults(),
+            tinted_rockets: false,
+            launch_trail: false,
+            mirror: false,
+            shape: None,
+            glow: false,
+            shockwave: false,
+            stars: 0,
+            comets: false,
+            comet_rate: 2.0,
+            bell: false,
+            background: None,
+            horizon: None,
+            invert: false,
+            additive: false,
+            long_exposure: None,
+            fade_curve: FadeCurve::Linear,
+            motion_blur: false,
+            blur_speed: 1.0,
+            smooth: false,
+            palette: None,
+            colors: None,
+            rainbow: false,
+            rainbow_step: 30.0,
+            hue_drift: 0.0,
+            chemistry: false,
+            element: None,
+            flash_core: false,
+            depth: 0.0,
+            two_tone: false,
+            max_fireworks: 100,
+            particles_per_firework: None,
+            script: None,
+            loop_script: false,
+            fps: 60,
+            duration: None,
+            status_bar: false,
+            record: None,
+            record_frames: 600,
+            record_replay: None,
+            play_replay: None,
+            stats_json: None,
+            ascii: false,
+            no_diff: false,
+            bench: None,
+        }
+    }
+}
+
+impl Args {
+    /// Parses the command line on top of the defaults from the config file.
+    pub fn parse() -> Result<Self> {
+        let defaults = Config::load()?.apply(Self::default())?;
+        defaults.apply(themes_first(std::env::args().skip(1).collect()))
+    }
+
+    /// Overrides these arguments with the ones given in `args`.
+    pub fn apply<I: IntoIterator<Item = String>>(self, args: I) -> Result<Self> {
+        let mut parsed = self;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => parsed.seed = Some(parse_value(&mut args, &arg)?),
+                "--density" => {
+                    parsed.density = parse_ranged(&mut args, &arg, 0.0..=1.0)?;
+                }
+                "--audio-reactive" => parsed.audio_reactive = true,
+                "--audio-threshold" => {
+                    parsed.audio_threshold = parse_ranged(&mut args, &arg, 0.01..=1.0)?;
+                }
+                "--steady" => parsed.steady = true,
+                "--idle-secs" => {
+                    parsed.idle_secs = Some(parse_ranged(&mut args, &arg, 0.0..=86400.0)?);
+                }
+                "--spread" => parsed.spread = parse_ranged(&mut args, &arg, 0.0..=1.0)?,
+                "--wind" => parsed.wind = parse_ranged(&mut args, &arg, -MAX_WIND..=MAX_WIND)?,
+                "--spark-size" => {
+                    parsed.spark_size = parse_value::<NonZeroU32, _>(&mut args, &arg)?.get();
+                }
+                "--vary-size" => parsed.vary_size = true,
+                "--rocket-width" => {
+                    parsed.rocket_width = parse_value::<NonZeroU32, _>(&mut args, &arg)?.get();
+                }
+                "--rocket-height" => {
+                    parsed.rocket_height = parse_value::<NonZeroU32, _>(&mut args, &arg)?.get();
+                }
+                "--spark-density" => {
+                    parsed.spark_density = parse_ranged(&mut args, &arg, 0.0..=20.0)?;
+                }
+                "--min-spark-frames" => {
+                    parsed.min_spark_frames = parse_ranged(&mut args, &arg, 0..=600)?;
+                }
+                "--max-age" => {
+                    parsed.max_age = parse_value::<NonZeroU32, _>(&mut args, &arg)?.get();
+                }
+                "--trail" => parsed.trail = parse_ranged(&mut args, &arg, 0..=MAX_TRAIL)?,
+                "--gravity" => parsed.gravity = parse_ranged(&mut args, &arg, 0.0..=0.5)?,
+                "--spread-angle" => {
+                    parsed.spread_angle = Some(parse_ranged(&mut args, &arg, 1.0..=360.0)?);
+                }
+                "--rise" => parsed.rise = parse_ranged(&mut args, &arg, 0.0..=2.0)?,
+                "--gravity-jitter" => {
+                    parsed.gravity_jitter = parse_ranged(&mut args, &arg, 0.0..=1.0)?;
+                }
+                "--drag" => parsed.drag = parse_ranged(&mut args, &arg, 0.0..=0.5)?,
+                "--attractors" => parsed.attractors = true,
+                "--boundary" => {
+                    let name = next_value(&mut args, &arg)?;
+                    parsed.boundary = Boundary::from_name(&name).ok_or_else(|| {
+                        anyhow!(
+                            "unknown boundary `{name}`, expected one of: {}",
+                            BOUNDARY_NAMES.join(", ")
+                        )
+                    })?;
+                }
+                "--ground" => parsed.ground = true,
+                "--kinds" => {
+                    let list = next_value(&mut args, &arg)?;
+                    parsed.kinds = list
+                        .split(',')
+                        .map(|name| {
+                            FireworkKind::from_name(name.trim()).ok_or_else(|| {
+                                anyhow!(
+                                    "unknown firework kind `{name}`, expected one of: {}",
+                                    FIREWORK_KIND_NAMES.join(", ")
+                                )
+                            })
+                        })
+                        .collect::<Result<_>>()?;
+                }
+                "--multistage" => parsed.multistage = true,
+                "--crackle" => parsed.crackle = true,
+                "--twinkle" => parsed.twinkle = true,
+                "--mortar" => parsed.mortar = true,
+                "--dud-rate" => parsed.dud_rate = parse_ranged(&mut args, &arg, 0.0..=1.0)?,
+                "--mortar-frames" => {
+                    parsed.mortar_frames = parse_ranged(&mut args, &arg, 1..=120)?;
+                }
+                "--tinted-rockets" => parsed.tinted_rockets = true,
+                "--launch-trail" => parsed.launch_trail = true,
+                "--cooling" => parsed.cooling = parse_ranged(&mut args, &arg, -360.0..=360.0)?,
+                "--mirror" => parsed.mirror = true,
+                "--shape" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.shape =
+                        Some(Shape::parse(&value).with_context(|| format!("invalid `{arg}`"))?);
+                }
+                "--glow" => parsed.glow = true,
+                "--shockwave" => parsed.shockwave = true,
+                "--stars" => parsed.stars = parse_ranged(&mut args, &arg, 0..=10000)?,
+                "--comets" => parsed.comets = true,
+                "--comet-rate" => parsed.comet_rate = parse_ranged(&mut args, &arg, 0.1..=60.0)?,
+                "--bell" => parsed.bell = true,
+                "--background" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.background =
+                        Some(Color::from_hex(&value).with_context(|| format!("invalid `{arg}`"))?);
+                }
+                "--horizon" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.horizon =
+                        Some(Color::from_hex(&value).with_context(|| format!("invalid `{arg}`"))?);
+                }
+                "--invert" => parsed.invert = true,
+                "--additive" => parsed.additive = true,
+                "--long-exposure" => {
+                    parsed.long_exposure = Some(parse_ranged(&mut args, &arg, 0.0..=1.0)?);
+                }
+                "--motion-blur" => parsed.motion_blur = true,
+                "--smooth" => parsed.smooth = true,
+                "--blur-speed" => parsed.blur_speed = parse_ranged(&mut args, &arg, 0.0..=10.0)?,
+                "--no-fade" => parsed.fade_curve = FadeCurve::Solid,
+                "--fade-curve" => {
+                    let name = next_value(&mut args, &arg)?;
+                    parsed.fade_curve = FadeCurve::from_name(&name).ok_or_else(|| {
+                        anyhow!(
+                            "unknown fade curve `{name}`, expected one of: {}",
+                            FADE_CURVE_NAMES.join(", ")
+                        )
+                    })?;
+                }
+                "--colors" => parsed.colors = Some(parse_ranged(&mut args, &arg, 1..=256)?),
+                "--theme" => {
+                    let name = next_value(&mut args, &arg)?;
+                    let flags = theme::theme(&name).ok_or_else(|| {
+                        anyhow!(
+                            "unknown theme `{name}`, expected one of: {}",
+                            THEME_NAMES.join(", ")
+                        )
+                    })?;
+                    let flags: Vec<String> = flags.iter().map(|flag| flag.to_string()).collect();
+                    parsed = parsed.apply(flags)?;
+                }
+                "--palette" => {
+                    let name = next_value(&mut args, &arg)?;
+                    parsed.palette = Some(palette::palette(&name).ok_or_else(|| {
+                        anyhow!(
+                            "unknown palette `{name}`, expected one of: {}",
+                            PALETTE_NAMES.join(", ")
+                        )
+                    })?);
+                }
+                "--palette-file" => {
+                    let path = PathBuf::from(next_value(&mut args, &arg)?);
+                    parsed.palette = Some(palette::load(&path)?);
+                }
+                "--rainbow" => parsed.rainbow = true,
+                "--rainbow-step" => {
+                    parsed.rainbow_step = parse_ranged(&mut args, &arg, -180.0..=180.0)?;
+                }
+                "--hue-drift" => parsed.hue_drift = parse_ranged(&mut args, &arg, -60.0..=60.0)?,
+                "--chemistry" => parsed.chemistry = true,
+                "--element" => {
+                    let name = next_value(&mut args, &arg)?;
+                    parsed.element = Some(Element::from_name(&name).ok_or_else(|| {
+                        anyhow!(
+                            "unknown element `{name}`, expected one of: {}",
+                            ELEMENT_NAMES.join(", ")
+                        )
+                    })?);
+                }
+                "--flash-core" => parsed.flash_core = true,
+                "--depth" => parsed.depth = parse_ranged(&mut args, &arg, 0.0..=1.0)?,
+                "--two-tone" => parsed.two_tone = true,
+                "--max-fireworks" => {
+                    parsed.max_fireworks = parse_value::<NonZeroUsize, _>(&mut args, &arg)?.get();
+                }
+                "--particles-per-firework" => {
+                    parsed.particles_per_firework =
+                        Some(parse_value::<NonZeroUsize, _>(&mut args, &arg)?.get());
+                }
+                "--script" => parsed.script = Some(next_value(&mut args, &arg)?.into()),
+                "--loop-script" => parsed.loop_script = true,
+                "--fps" => parsed.fps = parse_ranged(&mut args, &arg, 1..=240)?,
+                "--duration" => {
+                    parsed.duration = Some(parse_ranged(&mut args, &arg, 0.0..=86400.0)?);
+                }
+                "--status-bar" => parsed.status_bar = true,
+                "--record" => parsed.record = Some(next_value(&mut args, &arg)?.into()),
+                "--record-frames" => {
+                    parsed.record_frames = parse_value::<NonZeroUsize, _>(&mut args, &arg)?.get();
+                }
+                "--record-replay" => {
+                    parsed.record_replay = Some(next_value(&mut args, &arg)?.into());
+                }
+                "--play-replay" => parsed.play_replay = Some(next_value(&mut args, &arg)?.into()),
+                "--stats-json" => parsed.stats_json = Some(next_value(&mut args, &arg)?.into()),
+                "--ascii" => parsed.ascii = true,
+                "--no-diff" => parsed.no_diff = true,
+                "--bench" => {
+                    parsed.bench = Some(parse_value::<NonZeroUsize, _>(&mut args, &arg)?.get());
+                }
+                "-h" | "--help" => {
+                    print!("{USAGE}");
+                    std::process::exit(0);
+                }
+                other => bail!("unknown argument `{other}`\n\n{USAGE}"),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Moves every `--theme` with its value to the front of `args`, so the other
+/// flags override the theme wherever they are given.
+fn themes_first(args: Vec<String>) -> Vec<String> {
+    let mut themes = vec![];
+    let mut rest = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--theme" {
+            themes.push(arg);
+            themes.extend(args.next());
+        } else {
+            rest.push(arg);
+        }
+    }
+    themes.extend(rest);
+    themes
+}
+
+fn next_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String> {
+    args.next()
+        .ok_or_else(|| anyhow!("missing value for `{flag}`"))
+}
+
+fn parse_value<T, I>(args: &mut I, flag: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+    I: Iterator<Item = String>,
+{
+    let value = next_value(args, flag)?;
+    value
+        .parse()
+        .with_context(|| format!("invalid value `{value}` for `{flag}`"))
+}
+
+fn parse_ranged<T, I>(args: &mut I, flag: &str, range: RangeInclusive<T>) -> Result<T>
+where
+    T: FromStr + PartialOrd + Display,
+    T::Err: std::error::Error + Send + Sync + 'static,
+    I: Iterator<Item = String>,
+{
+    let value = parse_value(args, flag)?;
+    if !range.contains(&value) {
+        bail!(
+            "value {value} for `{flag}` is out of range, expected {} to {}",
+            range.start(),
+            range.end()
+        );
+    }
+    Ok(value)
+}
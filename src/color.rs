@@ -0,0 +1,106 @@
+//! Helpers on top of pixel_loop's [Color].
+
+use anyhow::{bail, Result};
+use pixel_loop::color::Color;
+
+pub trait Blend {
+    /// Mixes `a` and `b`, `t` = 0 giving `a` and `t` = 1 giving `b`.
+    fn blend(a: &Self, b: &Self, t: f64) -> Self;
+
+    /// Adds up the channels of `a` and `b`, saturating at full brightness.
+    fn add(a: &Self, b: &Self) -> Self;
+}
+
+impl Blend for Color {
+    fn blend(a: &Self, b: &Self, t: f64) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Color::from_rgb(mix(a.r, b.r), mix(a.g, b.g), mix(a.b, b.b))
+    }
+
+    fn add(a: &Self, b: &Self) -> Self {
+        Color::from_rgb(
+            a.r.saturating_add(b.r),
+            a.g.saturating_add(b.g),
+            a.b.saturating_add(b.b),
+        )
+    }
+}
+
+/// Mirrors the lightness of `color`, turning black into white and bright
+/// colors into dark ones of the same hue.
+///
+/// All channels are shifted by the same amount, so doing it twice gives back
+/// exactly the same color.
+pub fn mirror_lightness(color: &Color) -> Color {
+    let (max, min) = (
+        color.r.max(color.g).max(color.b) as i32,
+        color.r.min(color.g).min(color.b) as i32,
+    );
+    let shift = |channel: u8| (channel as i32 + 255 - max - min) as u8;
+    Color::from_rgb(shift(color.r), shift(color.g), shift(color.b))
+}
+
+pub trait FromHex: Sized {
+    /// Parses a color written as `#rrggbb` or the shorthand `#rgb`.
+    fn from_hex(text: &str) -> Result<Self>;
+}
+
+impl FromHex for Color {
+    fn from_hex(text: &str) -> Result<Self> {
+        let Some(digits) = text.strip_prefix('#') else {
+            bail!("invalid color `{text}`, expected #rgb or #rrggbb starting with `#`");
+        };
+        if let Some(invalid) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            bail!("invalid color `{text}`, `{invalid}` is not a hex digit");
+        }
+        let channel = |i: usize, len: usize| {
+            let value = u8::from_str_radix(&digits[i * len..(i + 1) * len], 16).unwrap();
+            // A single digit stands for itself repeated, `f` being `ff`.
+            if len == 1 {
+                value * 0x11
+            } else {
+                value
+            }
+        };
+        let len = match digits.len() {
+            3 => 1,
+            6 => 2,
+            count => {
+                bail!("invalid color `{text}`, expected #rgb or #rrggbb but got {count} digits")
+            }
+        };
+        Ok(Color::from_rgb(
+            channel(0, len),
+            channel(1, len),
+            channel(2, len),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_colors() {
+        let parse = |text| Color::from_hex(text).unwrap();
+        assert_eq!(parse("#fff"), Color::from_rgb(255, 255, 255));
+        assert_eq!(parse("#ffffff"), Color::from_rgb(255, 255, 255));
+        assert_eq!(parse("#000000"), Color::from_rgb(0, 0, 0));
+        assert_eq!(parse("#1A2b3C"), Color::from_rgb(0x1a, 0x2b, 0x3c));
+        assert_eq!(parse("#f80"), Color::from_rgb(0xff, 0x88, 0x00));
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        let error = |text| Color::from_hex(text).unwrap_err().to_string();
+        assert!(error("#ffff").contains("got 4 digits"));
+        assert!(error("#").contains("got 0 digits"));
+        assert!(error("#fffffff").contains("got 7 digits"));
+        assert!(error("#ggg").contains("`g` is not a hex digit"));
+        assert!(error("#12 45 6").contains("` ` is not a hex digit"));
+        assert!(error("ffffff").contains("starting with `#`"));
+        assert!(error("").contains("starting with `#`"));
+    }
+}
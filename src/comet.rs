@@ -0,0 +1,69 @@
+//! Comets streaking across the sky now and then, independent of fireworks.
+
+use pixel_loop::canvas::Canvas;
+use pixel_loop::color::Color;
+use pixel_loop::rand::Rng;
+use std::ops::Range;
+
+use crate::sim::{DrawStyle, Edges, Forces, Particle};
+
+/// Color of the head of a comet.
+const COLOR: Color = Color::from_rgb(220, 235, 255);
+/// Previous positions a comet leaves behind as its tail.
+const TAIL_LENGTH: usize = 12;
+/// Slowest and fastest horizontal speed of a comet in pixels per step.
+const SPEEDS: Range<f64> = 1.5..3.0;
+/// Slowest and fastest vertical speed of a comet relative to its horizontal
+/// one, keeping its path a shallow diagonal.
+const SLOPES: Range<f64> = 0.2..0.5;
+/// Brightness a comet has left when it reaches the far edge of the canvas.
+const FINAL_BRIGHTNESS: f64 = 0.2;
+
+/// A single bright particle flying in a straight line, unaffected by
+/// gravity, wind or anything else.
+pub struct Comet {
+    particle: Particle,
+}
+
+impl Comet {
+    /// A comet entering the top of a `width` pixels wide canvas and flying
+    /// down across it to the left or right.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R, width: u32) -> Self {
+        let speed = rng.gen_range(SPEEDS);
+        let slope = rng.gen_range(SLOPES);
+        // It starts in the half it flies away from, so it crosses most of
+        // the sky.
+        let half = width as f64 / 2.0;
+        let (x, direction) = if rng.gen_bool(0.5) {
+            (rng.gen_range(0.0..half.max(1.0)), 1.0)
+        } else {
+            (rng.gen_range(half..(width as f64).max(half + 1.0)), -1.0)
+        };
+        let crossing_steps = width as f64 / speed;
+        let particle = Particle::new(x.round() as i64, 0, 1, 1, COLOR)
+            .with_speed(direction * speed, slope * speed)
+            .with_trail(TAIL_LENGTH)
+            .with_fading((1.0 - FINAL_BRIGHTNESS) / crossing_steps.max(1.0));
+        Self { particle }
+    }
+
+    pub fn update(&mut self) {
+        let forces = Forces {
+            gravity: 0.0,
+            wind: 0.0,
+            drag: 0.0,
+            attractors: &[],
+        };
+        self.particle.update(&forces, &Edges::NONE);
+    }
+
+    pub fn draw<C: Canvas>(&self, canvas: &mut C, style: &DrawStyle) {
+        self.particle.draw(canvas, style);
+    }
+
+    /// Whether the comet burnt out or left a canvas of `width` x `height`
+    /// pixels.
+    pub fn is_dead(&self, width: f64, height: f64) -> bool {
+        self.particle.is_dead() || !self.particle.is_within(width, height)
+    }
+}
@@ -0,0 +1,120 @@
+//! Defaults for the command line arguments read from a config file.
+//!
+//! The file lives at `$XDG_CONFIG_HOME/terminal_fireworks.toml`, falling back
+//! to `~/.config/terminal_fireworks.toml`. Each key sets the default of the
+//! command line flag of the same name, with a `theme` being applied before
+//! the other keys:
+//!
+//! ```toml
+//! theme = "night"
+//! density = 0.2
+//! gravity = 0.03
+//! wind = -0.01
+//! palette = "pastel"
+//! fps = 30
+//! background = "#001020"
+//! ```
+//!
+//! Up to nine `presets` replace the fireworks the number keys launch, in the
+//! order of the keys, each with a kind and color and optionally factors for
+//! its size and number of sparks:
+//!
+//! ```toml
+//! [[presets]]
+//! kind = "willow"
+//! color = "#30ff60"
+//! size = 1.3
+//! sparks = 1.6
+//! ```
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Args;
+use crate::preset::{PresetConfig, MAX_PRESETS};
+
+const FILE_NAME: &str = "terminal_fireworks.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    theme: Option<String>,
+    density: Option<f64>,
+    gravity: Option<f64>,
+    wind: Option<f64>,
+    palette: Option<String>,
+    fps: Option<u32>,
+    background: Option<String>,
+    presets: Option<Vec<PresetConfig>>,
+}
+
+impl Config {
+    /// Reads the config file, or returns an empty config if there is none.
+    pub fn load() -> Result<Self> {
+        match default_path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("read config `{}`", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("invalid config `{}`", path.display()))
+    }
+
+    /// Applies the configured values on top of `args`.
+    ///
+    /// The values are checked the same way as the command line flags they
+    /// stand for.
+    pub fn apply(&self, mut args: Args) -> Result<Args> {
+        for (key, value) in self.values() {
+            let flag = format!("--{key}");
+            args = args
+                .apply([flag, value])
+                .with_context(|| format!("invalid `{key}` in config"))?;
+        }
+        if let Some(ref presets) = self.presets {
+            if presets.len() > MAX_PRESETS {
+                bail!("more than {MAX_PRESETS} `presets` in config");
+            }
+            args.presets = presets
+                .iter()
+                .enumerate()
+                .map(|(index, preset)| {
+                    preset
+                        .parse()
+                        .with_context(|| format!("invalid preset {} in config", index + 1))
+                })
+                .collect::<Result<_>>()?;
+        }
+        Ok(args)
+    }
+
+    /// The set keys with their values written as on the command line.
+    fn values(&self) -> Vec<(&'static str, String)> {
+        let mut values = vec![];
+        let mut push = |key, value: Option<String>| {
+            if let Some(value) = value {
+                values.push((key, value));
+            }
+        };
+        push("theme", self.theme.clone());
+        push("density", self.density.map(|v| v.to_string()));
+        push("gravity", self.gravity.map(|v| v.to_string()));
+        push("wind", self.wind.map(|v| v.to_string()));
+        push("palette", self.palette.clone());
+        push("fps", self.fps.map(|v| v.to_string()));
+        push("background", self.background.clone());
+        values
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join(FILE_NAME))
+}
@@ -0,0 +1,147 @@
+//! Light of bursts briefly brightening the sky around them.
+
+use pixel_loop::canvas::Canvas;
+use pixel_loop::color::Color;
+
+use crate::color::Blend;
+use crate::sim::{Burst, SpatialGrid};
+
+/// Frames a glow takes to fade out.
+const GLOW_FRAMES: f64 = 15.0;
+/// Brightness of the glow at its center when it starts, 1 being the full
+/// burst color.
+const GLOW_STRENGTH: f64 = 0.25;
+/// Radius in pixels of the glow of a burst without sparks.
+const BASE_RADIUS: f64 = 4.0;
+/// Radius in pixels added per spark of the burst.
+const RADIUS_PER_SPARK: f64 = 0.3;
+/// Largest radius in pixels, keeps the per pixel cost bounded.
+const MAX_RADIUS: f64 = 24.0;
+/// Distance in pixels below which bursts of the same step share a glow.
+const MERGE_DISTANCE: f64 = 8.0;
+
+pub struct Glow {
+    center: (f64, f64),
+    radius: f64,
+    color: Color,
+    /// Number of sparks of the bursts lighting the glow.
+    sparks: usize,
+    /// Brightness of the glow, fading from 1 to 0.
+    intensity: f64,
+}
+
+/// Radius in pixels of the glow of bursts with `sparks` sparks in total.
+fn radius(sparks: usize) -> f64 {
+    (BASE_RADIUS + sparks as f64 * RADIUS_PER_SPARK).min(MAX_RADIUS)
+}
+
+impl Glow {
+    /// A glow around `burst`, larger for bursts with more sparks.
+    pub fn new(burst: &Burst) -> Self {
+        Self {
+            center: burst.position,
+            radius: radius(burst.sparks),
+            color: burst.color,
+            sparks: burst.sparks,
+            intensity: 1.0,
+        }
+    }
+
+    /// Glows of `bursts` of the same step on a `width` x `height` canvas.
+    ///
+    /// Bursts close to each other, like the stages of a crossette, light a
+    /// single larger glow instead of adding up to a white blotch.
+    pub fn merged(bursts: &[Burst], width: u32, height: u32) -> Vec<Self> {
+        let mut glows: Vec<Self> = vec![];
+        let mut grid: SpatialGrid<usize> = SpatialGrid::new(width, height, MERGE_DISTANCE);
+        for burst in bursts {
+            let near = grid.neighbors(burst.position).copied().find(|&index| {
+                let (x, y) = glows[index].center;
+                let (dx, dy) = (burst.position.0 - x, burst.position.1 - y);
+                (dx * dx + dy * dy).sqrt() < MERGE_DISTANCE
+            });
+            match near {
+                Some(index) => glows[index].absorb(burst),
+                None => {
+                    grid.insert(burst.position, glows.len());
+                    glows.push(Self::new(burst));
+                }
+            }
+        }
+        glows
+    }
+
+    /// Adds the light of `burst` to this glow, mixing in its color by its
+    /// share of the sparks.
+    fn absorb(&mut self, burst: &Burst) {
+        let sparks = self.sparks + burst.sparks;
+        if sparks > 0 {
+            let share = burst.sparks as f64 / sparks as f64;
+            self.color = Color::blend(&self.color, &burst.color, share);
+        }
+        self.sparks = sparks;
+        self.radius = radius(sparks);
+    }
+
+    pub fn update(&mut self) {
+        self.intensity -= 1.0 / GLOW_FRAMES;
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.intensity <= 0.0
+    }
+
+    /// Adds the light of the glow to the pixels within its radius, brightest
+    /// at the center.
+    pub fn draw<C: Canvas>(&self, canvas: &mut C) {
+        if self.is_dead() {
+            return;
+        }
+        let black = Color::from_rgb(0, 0, 0);
+        let (cx, cy) = self.center;
+        let left = (cx - self.radius).floor().max(0.0) as i64;
+        let right = (cx + self.radius).ceil().min(canvas.width() as f64 - 1.0) as i64;
+        let top = (cy - self.radius).floor().max(0.0) as i64;
+        let bottom = (cy + self.radius).ceil().min(canvas.height() as f64 - 1.0) as i64;
+        for y in top..=bottom {
+            for x in left..=right {
+                let distance = ((x as f64 - cx).powi(2) + (y as f64 - cy).powi(2)).sqrt();
+                if distance >= self.radius {
+                    continue;
+                }
+                let falloff = 1.0 - distance / self.radius;
+                let brightness = falloff * self.intensity * GLOW_STRENGTH;
+                let light = Color::blend(&black, &self.color, brightness);
+                if let Some(below) = canvas.maybe_get(x, y) {
+                    let color = Color::add(below, &light);
+                    canvas.set(x as u32, y as u32, &color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn burst(x: f64, color: Color) -> Burst {
+        Burst {
+            position: (x, 20.0),
+            color,
+            sparks: 10,
+        }
+    }
+
+    #[test]
+    fn close_bursts_share_a_glow() {
+        let (red, blue) = (Color::from_rgb(200, 0, 0), Color::from_rgb(0, 0, 200));
+        let bursts = [burst(10.0, red), burst(13.0, blue), burst(40.0, red)];
+        let glows = Glow::merged(&bursts, 80, 48);
+        assert_eq!(glows.len(), 2);
+        assert_eq!(glows[0].sparks, 20);
+        assert_eq!(glows[0].color, Color::from_rgb(100, 0, 100));
+        assert_eq!(glows[0].radius, radius(20));
+        assert_eq!(glows[1].radius, radius(10));
+    }
+}
@@ -0,0 +1,434 @@
+//! Terminal input handling.
+//!
+//! pixel_loop's `CrosstermInputState` reads every pending crossterm event but
+//! only keeps keyboard input. This mirrors its keyboard handling and
+//! additionally records mouse clicks and terminal resizes.
+
+use anyhow::Result;
+use crossterm::event::{
+    DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
+    KeyboardEnhancementFlags, MouseButton, MouseEvent, MouseEventKind, PopKeyboardEnhancementFlags,
+    PushKeyboardEnhancementFlags,
+};
+use crossterm::style::ResetColor;
+use crossterm::{cursor, execute};
+use pixel_loop::input::{InputState, KeyboardKey, KeyboardState};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::replay;
+
+/// A mouse button press in terminal cell coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseClick {
+    pub button: MouseButton,
+    pub column: u16,
+    pub row: u16,
+}
+
+pub struct TerminalInputState {
+    keys_down: HashMap<KeyboardKey, usize>,
+    keys_pressed_this_update: HashSet<KeyboardKey>,
+    keys_released_this_update: HashSet<KeyboardKey>,
+    clicks_this_update: Vec<MouseClick>,
+    resized_this_update: Option<(u16, u16)>,
+    event_cycles_before_released: usize,
+    enhanced_keyboard: bool,
+    /// Whether input is read from the terminal at all.
+    attached: bool,
+    /// Whether Ctrl-C was pressed.
+    interrupted: bool,
+    /// Whether a replay reported a key the show doesn't react to.
+    replayed_input: bool,
+}
+
+impl TerminalInputState {
+    pub fn new() -> Self {
+        Self {
+            keys_down: HashMap::new(),
+            keys_pressed_this_update: HashSet::new(),
+            keys_released_this_update: HashSet::new(),
+            clicks_this_update: vec![],
+            resized_this_update: None,
+            event_cycles_before_released: 2,
+            enhanced_keyboard: false,
+            attached: true,
+            interrupted: false,
+            replayed_input: false,
+        }
+    }
+
+    /// Input which never touches the terminal and never reports anything, for
+    /// output which isn't shown in it.
+    pub fn detached() -> Self {
+        Self {
+            attached: false,
+            ..Self::new()
+        }
+    }
+
+    /// Whether any key or mouse button was pressed since the last update.
+    pub fn has_input(&self) -> bool {
+        !self.keys_pressed_this_update.is_empty()
+            || !self.clicks_this_update.is_empty()
+            || self.replayed_input
+    }
+
+    /// Whether Ctrl-C was pressed, which should end the show right away.
+    pub fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    /// Mouse buttons pressed since the last update.
+    pub fn mouse_clicks(&self) -> &[MouseClick] {
+        &self.clicks_this_update
+    }
+
+    /// New terminal size in columns and rows, if it changed since the last
+    /// update.
+    pub fn resized(&self) -> Option<(u16, u16)> {
+        self.resized_this_update
+    }
+
+    /// Everything reported since the last update, as events of a replay.
+    pub fn events(&self) -> Vec<replay::Event> {
+        let mut events: Vec<replay::Event> = self
+            .keys_pressed_this_update
+            .iter()
+            .filter(|&&key| replay::key_name(key).is_some())
+            .map(|&key| replay::Event::Key(key))
+            .collect();
+        if events.len() < self.keys_pressed_this_update.len() {
+            events.push(replay::Event::Input);
+        }
+        events.extend(
+            self.clicks_this_update
+                .iter()
+                .map(|&click| replay::Event::Click(click)),
+        );
+        if let Some((columns, rows)) = self.resized_this_update {
+            events.push(replay::Event::Resize(columns, rows));
+        }
+        if self.interrupted {
+            events.push(replay::Event::Interrupt);
+        }
+        events
+    }
+
+    /// Reports the `events` of a replay instead of the keys, clicks and
+    /// resizes read from the terminal. Ctrl-C still ends the show.
+    pub fn replay(&mut self, events: &[replay::Event]) {
+        self.keys_pressed_this_update.clear();
+        self.clicks_this_update.clear();
+        self.resized_this_update = None;
+        self.replayed_input = false;
+        for event in events {
+            match *event {
+                replay::Event::Key(key) => {
+                    self.keys_pressed_this_update.insert(key);
+                }
+                replay::Event::Input => self.replayed_input = true,
+                replay::Event::Click(click) => self.clicks_this_update.push(click),
+                replay::Event::Resize(columns, rows) => {
+                    self.resized_this_update = Some((columns, rows))
+                }
+                replay::Event::Interrupt => self.interrupted = true,
+            }
+        }
+    }
+
+    /// Puts the terminal back into the state it was in before `begin`.
+    fn restore_terminal(&self) -> Result<()> {
+        if !self.attached {
+            return Ok(());
+        }
+        if self.enhanced_keyboard {
+            execute!(std::io::stdout(), PopKeyboardEnhancementFlags)?;
+        }
+        execute!(std::io::stdout(), DisableMouseCapture)?;
+        crossterm::terminal::disable_raw_mode()?;
+        Ok(())
+    }
+
+    /// Restores the terminal before the message of any panic is printed, as
+    /// the show can't clean up after itself then.
+    pub fn restore_on_panic(&self) {
+        if !self.attached {
+            return;
+        }
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            // Unknown escape sequences are ignored by terminals, so they can
+            // be sent without knowing what was enabled.
+            let _ = execute!(
+                std::io::stdout(),
+                PopKeyboardEnhancementFlags,
+                DisableMouseCapture,
+                ResetColor,
+                cursor::Show
+            );
+            let _ = crossterm::terminal::disable_raw_mode();
+            previous(info);
+        }));
+    }
+
+    fn press_key(&mut self, key: KeyboardKey) {
+        if self
+            .keys_down
+            .insert(key, self.event_cycles_before_released)
+            .is_none()
+        {
+            self.keys_pressed_this_update.insert(key);
+        }
+    }
+
+    fn handle_mouse(&mut self, event: &MouseEvent) {
+        if let MouseEventKind::Down(button) = event.kind {
+            self.clicks_this_update.push(MouseClick {
+                button,
+                column: event.column,
+                row: event.row,
+            });
+        }
+    }
+
+    fn next_loop_fallback(&mut self, next_events: Vec<Event>) {
+        let removed_keys_down = decrement_key_ref_counts(&mut self.keys_down);
+        self.keys_pressed_this_update.clear();
+        self.keys_released_this_update.clear();
+
+        for event in next_events {
+            match event {
+                Event::Key(KeyEvent {
+                    kind: KeyEventKind::Press,
+                    ref code,
+                    ..
+                }) => {
+                    if let Some(key) = map_keycode(code) {
+                        self.press_key(key);
+                    }
+                }
+                Event::Mouse(ref mouse) => self.handle_mouse(mouse),
+                Event::Resize(columns, rows) => self.resized_this_update = Some((columns, rows)),
+                _ => {}
+            }
+        }
+
+        for removed_key in removed_keys_down {
+            if !self.keys_down.contains_key(&removed_key) {
+                self.keys_released_this_update.insert(removed_key);
+            }
+        }
+    }
+
+    fn next_loop_enhanced(&mut self, next_events: Vec<Event>) {
+        self.keys_pressed_this_update.clear();
+        self.keys_released_this_update.clear();
+
+        for event in next_events {
+            match event {
+                Event::Key(KeyEvent {
+                    ref kind, ref code, ..
+                }) => {
+                    if let Some(key) = map_keycode(code) {
+                        match kind {
+                            KeyEventKind::Press => self.press_key(key),
+                            KeyEventKind::Release => {
+                                if self.keys_down.remove(&key).is_some() {
+                                    self.keys_released_this_update.insert(key);
+                                }
+                            }
+                            KeyEventKind::Repeat => {}
+                        }
+                    }
+                }
+                Event::Mouse(ref mouse) => self.handle_mouse(mouse),
+                Event::Resize(columns, rows) => self.resized_this_update = Some((columns, rows)),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl InputState for TerminalInputState {
+    fn begin(&mut self) -> Result<()> {
+        if !self.attached {
+            return Ok(());
+        }
+        crossterm::terminal::enable_raw_mode()?;
+        // Terminals which never answer the query simply get basic mode.
+        if crossterm::terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            self.enhanced_keyboard = true;
+            execute!(
+                std::io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        execute!(std::io::stdout(), EnableMouseCapture)?;
+        Ok(())
+    }
+
+    fn next_loop(&mut self) -> Result<()> {
+        if !self.attached {
+            return Ok(());
+        }
+        let next_events = get_all_next_events()?;
+        // Raw mode delivers Ctrl-C as a key instead of a signal.
+        self.interrupted |= next_events.iter().any(|event| {
+            matches!(
+                event,
+                Event::Key(KeyEvent {
+                    kind: KeyEventKind::Press,
+                    code: KeyCode::Char('c') | KeyCode::Char('C'),
+                    modifiers: KeyModifiers::CONTROL,
+                    ..
+                })
+            )
+        });
+
+        self.clicks_this_update.clear();
+        self.resized_this_update = None;
+        if self.enhanced_keyboard {
+            self.next_loop_enhanced(next_events);
+        } else {
+            self.next_loop_fallback(next_events);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.restore_terminal()?;
+        self.enhanced_keyboard = false;
+        Ok(())
+    }
+}
+
+impl KeyboardState for TerminalInputState {
+    fn is_key_pressed(&self, key: KeyboardKey) -> bool {
+        self.keys_pressed_this_update.contains(&key)
+    }
+
+    fn is_key_down(&self, key: KeyboardKey) -> bool {
+        self.keys_down.contains_key(&key)
+    }
+
+    fn is_key_released(&self, key: KeyboardKey) -> bool {
+        self.keys_released_this_update.contains(&key)
+    }
+
+    fn is_key_up(&self, key: KeyboardKey) -> bool {
+        !self.keys_down.contains_key(&key)
+    }
+}
+
+fn get_all_next_events() -> Result<Vec<Event>> {
+    use crossterm::event::{poll, read};
+    let mut events = vec![];
+    while poll(Duration::from_secs(0))? {
+        events.push(read()?);
+    }
+    Ok(events)
+}
+
+/// Counts down how long keys are held in basic keyboard mode, where the
+/// terminal never reports releases. Returns the keys which ran out.
+fn decrement_key_ref_counts(keys: &mut HashMap<KeyboardKey, usize>) -> Vec<KeyboardKey> {
+    let mut removed_keys = vec![];
+    keys.retain(|key, refcount| {
+        *refcount = refcount.saturating_sub(1);
+        if *refcount == 0 {
+            removed_keys.push(*key);
+            return false;
+        }
+        true
+    });
+    removed_keys
+}
+
+fn map_keycode(keycode: &KeyCode) -> Option<KeyboardKey> {
+    match keycode {
+        KeyCode::Backspace => Some(KeyboardKey::Backspace),
+        KeyCode::Enter => Some(KeyboardKey::Enter),
+        KeyCode::Left => Some(KeyboardKey::Left),
+        KeyCode::Right => Some(KeyboardKey::Right),
+        KeyCode::Up => Some(KeyboardKey::Up),
+        KeyCode::Down => Some(KeyboardKey::Down),
+        KeyCode::Home => Some(KeyboardKey::Home),
+        KeyCode::End => Some(KeyboardKey::End),
+        KeyCode::PageUp => Some(KeyboardKey::PageUp),
+        KeyCode::PageDown => Some(KeyboardKey::PageDown),
+        KeyCode::Tab => Some(KeyboardKey::Tab),
+        KeyCode::Delete => Some(KeyboardKey::Delete),
+        KeyCode::Insert => Some(KeyboardKey::Insert),
+        KeyCode::Esc => Some(KeyboardKey::Escape),
+        KeyCode::F(number) => match number {
+            1 => Some(KeyboardKey::F1),
+            2 => Some(KeyboardKey::F2),
+            3 => Some(KeyboardKey::F3),
+            4 => Some(KeyboardKey::F4),
+            5 => Some(KeyboardKey::F5),
+            6 => Some(KeyboardKey::F6),
+            7 => Some(KeyboardKey::F7),
+            8 => Some(KeyboardKey::F8),
+            9 => Some(KeyboardKey::F9),
+            10 => Some(KeyboardKey::F10),
+            11 => Some(KeyboardKey::F11),
+            12 => Some(KeyboardKey::F12),
+            _ => None,
+        },
+        KeyCode::Char(character) => match character.to_ascii_lowercase() {
+            '0' => Some(KeyboardKey::Zero),
+            '1' => Some(KeyboardKey::One),
+            '2' => Some(KeyboardKey::Two),
+            '3' => Some(KeyboardKey::Three),
+            '4' => Some(KeyboardKey::Four),
+            '5' => Some(KeyboardKey::Five),
+            '6' => Some(KeyboardKey::Six),
+            '7' => Some(KeyboardKey::Seven),
+            '8' => Some(KeyboardKey::Eight),
+            '9' => Some(KeyboardKey::Nine),
+            'a' => Some(KeyboardKey::A),
+            'b' => Some(KeyboardKey::B),
+            'c' => Some(KeyboardKey::C),
+            'd' => Some(KeyboardKey::D),
+            'e' => Some(KeyboardKey::E),
+            'f' => Some(KeyboardKey::F),
+            'g' => Some(KeyboardKey::G),
+            'h' => Some(KeyboardKey::H),
+            'i' => Some(KeyboardKey::I),
+            'j' => Some(KeyboardKey::J),
+            'k' => Some(KeyboardKey::K),
+            'l' => Some(KeyboardKey::L),
+            'm' => Some(KeyboardKey::M),
+            'n' => Some(KeyboardKey::N),
+            'o' => Some(KeyboardKey::O),
+            'p' => Some(KeyboardKey::P),
+            'q' => Some(KeyboardKey::Q),
+            'r' => Some(KeyboardKey::R),
+            's' => Some(KeyboardKey::S),
+            't' => Some(KeyboardKey::T),
+            'u' => Some(KeyboardKey::U),
+            'v' => Some(KeyboardKey::V),
+            'w' => Some(KeyboardKey::W),
+            'x' => Some(KeyboardKey::X),
+            'y' => Some(KeyboardKey::Y),
+            'z' => Some(KeyboardKey::Z),
+            '\'' => Some(KeyboardKey::Apostrophe),
+            ',' => Some(KeyboardKey::Comma),
+            '-' => Some(KeyboardKey::Minus),
+            '.' => Some(KeyboardKey::Period),
+            '/' => Some(KeyboardKey::Slash),
+            ';' => Some(KeyboardKey::Semicolon),
+            '=' => Some(KeyboardKey::Equal),
+            // Shifted `=` on most layouts, so it is treated as the same key.
+            '+' => Some(KeyboardKey::Equal),
+            '[' => Some(KeyboardKey::LeftBracket),
+            '\\' => Some(KeyboardKey::Backslash),
+            ']' => Some(KeyboardKey::RightBracket),
+            '`' => Some(KeyboardKey::Grave),
+            ' ' => Some(KeyboardKey::Space),
+            _ => None,
+        },
+        _ => None,
+    }
+}
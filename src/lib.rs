@@ -0,0 +1,1691 @@
+//! Fireworks in the terminal.
+//!
+//! The binary only calls [run_cli]. Other renderers can drive the show
+//! themselves: create a [State] from [cli::Args], advance it with [tick] and
+//! draw it onto any pixel_loop canvas with [draw_scene], such as a
+//! [NoopCanvas] when nothing needs to be shown. [State::stats] counts what
+//! is in the sky.
+
+use anyhow::Result;
+use canvas::{AnsiCanvas, RedrawCanvas, ShowCanvas, Sky};
+use chemistry::Element;
+use color::Blend;
+use comet::Comet;
+use crossterm::event::MouseButton;
+use crossterm::execute;
+use crossterm::terminal::{self, ClearType};
+use crossterm::tty::IsTty;
+use glow::Glow;
+use input::TerminalInputState;
+use overlay::{TextLine, TextOverlay};
+use pixel_loop::canvas::{Canvas, CrosstermCanvas};
+use pixel_loop::color::{Color, HslColor};
+use pixel_loop::input::{InputState, KeyboardKey, KeyboardState};
+use pixel_loop::rand::{Rng, SeedableRng};
+use pixel_loop::rand_xoshiro::Xoshiro256PlusPlus;
+use preset::{FireworkPreset, MAX_PRESETS};
+use record::GifRecorder;
+use replay::{Playback, Replay};
+use script::ScheduledLaunch;
+use shockwave::Shockwave;
+use sim::{Attractor, DrawStyle, Dud, FadeCurve, Firework, FireworkKind, ParticlePool};
+pub use sim::{NoopCanvas, SimParams};
+use star::Star;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use summary::RunSummary;
+
+mod audio;
+mod bench;
+mod canvas;
+mod chemistry;
+pub mod cli;
+mod color;
+mod comet;
+mod config;
+mod glow;
+mod input;
+mod overlay;
+mod palette;
+mod preset;
+mod record;
+mod replay;
+mod screenshot;
+mod script;
+mod shape;
+mod shockwave;
+mod sim;
+mod star;
+mod summary;
+mod terminal_theme;
+mod theme;
+
+/// Strongest wind that can be configured or nudged to.
+const MAX_WIND: f64 = 0.1;
+/// Change of the wind per arrow key press.
+const WIND_STEP: f64 = 0.005;
+/// Simulation steps per second, which all per step physics values like the
+/// gravity are relative to.
+const STEPS_PER_SECOND: f64 = 60.0;
+/// Strongest sideways speed of randomly launched rockets.
+const MAX_LAUNCH_DRIFT: f64 = 0.15;
+/// Updates the show fades out for after quitting.
+const QUIT_FADE_FRAMES: u32 = 30;
+/// Simulation steps a finale lasts, about two seconds.
+const FINALE_FRAMES: u32 = 120;
+/// Chance per simulation step to launch a firework during a finale.
+const FINALE_SPAWN_CHANCE: f64 = 0.95;
+/// Chance per simulation step to launch a firework once the show is left
+/// alone for the idle time.
+const IDLE_SPAWN_CHANCE: f64 = 0.4;
+/// Seconds the show takes to get lively after going idle, and to calm down
+/// again on input.
+const IDLE_RAMP_SECS: f64 = 3.0;
+/// Saturation in percent of launches stepping through the color wheel.
+const RAINBOW_SATURATION: f64 = 100.0;
+/// Lightness in percent of launches stepping through the color wheel.
+const RAINBOW_LIGHTNESS: f64 = 50.0;
+/// Degrees random launch colors differ from the drifting base hue by at most.
+const DRIFT_HUE_RANGE: f64 = 40.0;
+/// Saturation in percent of random launch colors around the drifting hue.
+const DRIFT_SATURATION: RangeInclusive<f64> = 60.0..=100.0;
+/// Lightness in percent of random launch colors around the drifting hue.
+const DRIFT_LIGHTNESS: f64 = 55.0;
+/// Most attractors at once, placing another one removes the oldest.
+const MAX_ATTRACTORS: usize = 8;
+/// Pull of a placed attractor, matching the default gravity at a distance
+/// of 10 pixels.
+const ATTRACTOR_STRENGTH: f64 = 2.0;
+/// Degrees the hue of the color picker turns by per arrow key press.
+const PICKER_HUE_STEP: f64 = 10.0;
+/// Percent the lightness of the color picker changes by per arrow key press.
+const PICKER_LIGHTNESS_STEP: f64 = 5.0;
+/// Lightest and darkest color the color picker allows, in percent.
+const PICKER_LIGHTNESS: RangeInclusive<f64> = 10.0..=90.0;
+/// Saturation in percent of colors picked with the color picker.
+const PICKER_SATURATION: f64 = 100.0;
+/// Cells the color swatch of the color picker is wide.
+const PICKER_SWATCH_WIDTH: usize = 6;
+/// Keys launching the presets, in order.
+const PRESET_KEYS: [KeyboardKey; MAX_PRESETS] = [
+    KeyboardKey::One,
+    KeyboardKey::Two,
+    KeyboardKey::Three,
+    KeyboardKey::Four,
+    KeyboardKey::Five,
+    KeyboardKey::Six,
+    KeyboardKey::Seven,
+    KeyboardKey::Eight,
+    KeyboardKey::Nine,
+];
+/// Burst sizes of beats from the quietest to the loudest.
+const BEAT_SIZES: RangeInclusive<f64> = 0.6..=1.4;
+/// Spark factors of beats from the quietest to the loudest.
+const BEAT_SPARKS: RangeInclusive<f64> = 0.5..=2.0;
+/// Brightness of the color of the quietest beats, the loudest having their
+/// full color.
+const BEAT_MIN_BRIGHTNESS: f64 = 0.4;
+/// Slowest and fastest the simulation can be played.
+const TIME_SCALES: RangeInclusive<f64> = 0.1..=4.0;
+/// Factor the time scale changes by per `+` or `-` key press.
+const TIME_SCALE_FACTOR: f64 = 1.5;
+/// Seconds a notice like the path of a saved screenshot stays on screen.
+const NOTICE_SECS: f64 = 3.0;
+
+/// Everything going on in the show.
+pub struct State {
+    fireworks: Vec<Firework>,
+    /// Particles of dead fireworks, reused for new bursts.
+    pool: ParticlePool,
+    /// Whether bursts light up the sky around them.
+    glow: bool,
+    /// Light of recent bursts, drawn below the sparks.
+    glows: Vec<Glow>,
+    /// Whether bursts send out an expanding ring.
+    shockwave: bool,
+    /// Rings of recent bursts, drawn below the sparks.
+    shockwaves: Vec<Shockwave>,
+    /// Number of stars in the sky.
+    star_count: usize,
+    /// Stars in the sky, placed on the first step.
+    stars: Vec<Star>,
+    /// Chance per step of a comet entering the sky.
+    comet_chance: f64,
+    /// Comets currently crossing the sky.
+    comets: Vec<Comet>,
+    /// Random numbers of the stars and comets, seeded on the first step and
+    /// apart from those deciding launches, which played back shows don't
+    /// draw.
+    sky_rng: Option<Xoshiro256PlusPlus>,
+    /// Whether bursts ring the terminal bell.
+    bell: bool,
+    /// Whether a burst happened since the last render, which rings the bell.
+    ring: bool,
+    /// Whether launches are evenly spaced instead of random, at the same
+    /// average rate.
+    steady: bool,
+    /// Share of a launch carried over to the next step when launching
+    /// steadily.
+    launch_budget: f64,
+    /// Simulation steps without input after which the show gets livelier,
+    /// never without one.
+    idle_frames: Option<u64>,
+    /// Simulation step of the last key press or mouse click.
+    last_input_frame: u64,
+    /// How far the show has ramped up towards its idle density, from 0 to 1.
+    liveliness: f64,
+    /// How far random launches spread from the center, 0 launching all of
+    /// them at the center and 1 anywhere across the width.
+    spread: f64,
+    /// Simulation steps left of the finale, which launches fireworks nearly
+    /// every step while running.
+    finale_frames: u32,
+    /// Freezes the simulation while the last frame stays on screen.
+    paused: bool,
+    /// How key presses are interpreted.
+    input_mode: InputMode,
+    /// Color of the latest launch, which the color picker starts with.
+    last_launch_color: Color,
+    /// Speed of the show relative to normal, below 1 for slow motion.
+    time_scale: f64,
+    /// Simulation steps per update at normal speed, so the show runs equally
+    /// fast at any update rate.
+    steps_per_update: f64,
+    /// Fraction of a simulation step carried over to the next frame.
+    step_budget: f64,
+    /// Canvas and physics of the last simulation step, see [SimParams].
+    params: SimParams,
+    /// Whether attractors can be placed with the right mouse button.
+    place_attractors: bool,
+    /// Invisible points bending the paths of sparks, oldest first.
+    attractors: Vec<Attractor>,
+    /// Width and height of burst sparks in pixels.
+    spark_size: u32,
+    /// Number of previous positions each spark leaves behind.
+    trail_length: usize,
+    /// Whether some sparks burst a second time.
+    multistage: bool,
+    /// Whether dying sparks flicker.
+    crackle: bool,
+    /// Degrees the hue of sparks turns by over their lifetime.
+    cooling: f64,
+    /// Whether sparks leave short lived twinkles behind.
+    twinkle: bool,
+    /// Steps the mortar flashes for before a rocket rises, 0 for none.
+    mortar_frames: u32,
+    /// Whether rising rockets are tinted with their burst color.
+    tinted_rockets: bool,
+    /// Whether rising rockets leave sparks behind.
+    launch_trail: bool,
+    /// Whether every firework is mirrored across the center of the canvas.
+    mirror: bool,
+    /// Largest fraction by which gravity and drag of a spark randomly differ.
+    gravity_jitter: f64,
+    /// Degrees of the cone around straight up sparks are sprayed into.
+    spread_angle: Option<f64>,
+    /// Factor applied to the upward bias of sprayed sparks.
+    rise: f64,
+    /// Chance of a launched rocket to fizzle out or burst weakly.
+    dud_rate: f64,
+    /// Most sparks each firework keeps, never limited without a number.
+    particles_per_firework: Option<usize>,
+    /// Fireworks launched by the number keys, in order.
+    presets: Vec<FireworkPreset>,
+    /// Offsets of the sparks of shaped bursts from their center.
+    shape: Option<Rc<[(f64, f64)]>>,
+    /// Width and height of rising rockets in pixels.
+    rocket_size: (u32, u32),
+    /// Sparks added to a sphere burst per 1000 canvas pixels.
+    spark_density: f64,
+    /// Color of the sky, which sparks fade into.
+    background: Color,
+    /// Kinds random launches pick from, any kind if empty.
+    kinds: Vec<FireworkKind>,
+    /// Color the sky blends into towards the bottom, if not all of it is
+    /// the `background`.
+    horizon: Option<Color>,
+    /// Whether the lightness of everything is mirrored for light skies, dark
+    /// sparks fading into a light background.
+    invert: bool,
+    /// Whether overlapping sparks add up their light.
+    additive: bool,
+    /// Fraction of the light kept per simulation step with a long exposure,
+    /// which accumulates the frames instead of clearing them.
+    exposure: Option<f64>,
+    /// Simulation steps taken since the last frame was drawn.
+    unexposed_steps: u32,
+    /// How sparks dim as they burn out.
+    fade_curve: FadeCurve,
+    /// Speed in pixels per step above which sparks are drawn as streaks,
+    /// never without one.
+    motion_blur: Option<f64>,
+    /// Whether sparks are antialiased across the pixels around them.
+    smooth: bool,
+    /// Colors to pick launches from, random colors without one.
+    palette: Option<Vec<Color>>,
+    /// Number of random colors shared by all launches without a palette, a
+    /// new one for every launch without a count.
+    color_count: Option<usize>,
+    /// The random colors shared by all launches, picked on the first one.
+    anchor_colors: Vec<Color>,
+    /// Whether launch colors are those of metal salts, taking precedence
+    /// over the palette.
+    chemistry: bool,
+    /// Metal salt every launch is colored with, taking precedence over both.
+    element: Option<Element>,
+    /// Degrees the hue turns by from launch to launch, which then step
+    /// through the color wheel instead of using the palette.
+    rainbow_step: Option<f64>,
+    /// Hue of the next launch while stepping through the color wheel.
+    hue_cursor: f64,
+    /// Degrees the base hue turns by per step, never drifting without one.
+    hue_drift: Option<f64>,
+    /// Center of the hues of random launches and offset of the palette,
+    /// slowly turning with the hue drift.
+    base_hue: f64,
+    /// Whether bursts flash white at their center.
+    flash_core: bool,
+    /// Whether bursts get a second color for their inner sparks.
+    two_tone: bool,
+    /// Whether some burst sparks are bigger than others.
+    vary_size: bool,
+    /// Fewest simulation steps every spark lasts.
+    min_spark_frames: u32,
+    /// Simulation steps after which a firework is removed.
+    max_age: u32,
+    /// How much faster sparks look farther away, from 0 to 1.
+    depth_fade: f64,
+    /// Launches of a scripted show sorted by frame, which replace the random
+    /// ones.
+    script: Vec<ScheduledLaunch>,
+    /// Volumes of the beats heard with `--audio-reactive`, which replace
+    /// the random launches.
+    beats: Option<Receiver<f64>>,
+    /// Whether beats replace the random launches, heard from `beats` or
+    /// played back from a replay.
+    audio_reactive: bool,
+    /// Index of the next launch in `script`.
+    next_launch: usize,
+    /// Whether the script starts over once all its launches are done.
+    loop_script: bool,
+    /// Simulation step the current round of the script started at.
+    script_start: u64,
+    /// Simulation steps taken so far.
+    frame: u64,
+    /// Number of fireworks in flight at which no more are launched.
+    max_fireworks: usize,
+    /// Whether the frame rate and particle counts are shown.
+    show_stats: bool,
+    /// Whether the bottom row of the terminal shows what the show is doing
+    /// instead of the canvas.
+    status_bar: bool,
+    /// Smoothed number of rendered frames per second.
+    fps: f64,
+    overlay: TextOverlay,
+    /// Counters of the run so far, for the summary written at the end.
+    summary: RunSummary,
+    /// Captures the rendered frames while recording.
+    recorder: Option<GifRecorder>,
+    /// Whether the next rendered frame is saved as a screenshot.
+    screenshot_requested: bool,
+    /// Notes for the user, printed once the terminal is restored.
+    messages: Vec<String>,
+    /// Latest note shown on top of the show right away, with the elapsed
+    /// time it disappears at.
+    notice: Option<(String, Duration)>,
+    /// Input and launches of the show so far, while recording a replay.
+    replay: Option<Replay>,
+    /// Replay the show plays back instead of reacting to input and
+    /// launching randomly.
+    playback: Option<Playback>,
+    /// Number of updates so far.
+    updates: u64,
+    /// How long the show runs before quitting by itself, forever without one.
+    duration: Option<Duration>,
+    /// Time the show has been running for.
+    elapsed: Duration,
+    /// Updates left of the fade out after quitting.
+    quitting: Option<u32>,
+    /// Whether the show is over and the main loop should stop.
+    finished: bool,
+}
+
+/// How key presses are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum InputMode {
+    /// Keys control the show.
+    Show,
+    /// The arrow keys pick the color of all launches, with the hue in degrees
+    /// and lightness in percent picked so far.
+    ColorPicker { hue: f64, lightness: f64 },
+}
+
+/// Snapshot of the scene, for the stats line and benchmarks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SceneStats {
+    /// Fireworks in flight, including dead ones not yet recycled and the
+    /// reflections of mirrored ones.
+    pub fireworks: usize,
+    /// Live particles of all fireworks and reflections, counting rising
+    /// rockets as one.
+    pub particles: usize,
+    /// Fireworks which burnt out and get recycled on the next step.
+    pub dead: usize,
+}
+
+impl State {
+    /// A show with nothing launched yet, configured by `args`.
+    pub fn new(args: &cli::Args) -> Self {
+        Self {
+            fireworks: vec![],
+            pool: ParticlePool::default(),
+            glow: args.glow,
+            glows: vec![],
+            shockwave: args.shockwave,
+            shockwaves: vec![],
+            star_count: args.stars,
+            stars: vec![],
+            comet_chance: if args.comets {
+                args.comet_rate / 60.0 / STEPS_PER_SECOND
+            } else {
+                0.0
+            },
+            comets: vec![],
+            sky_rng: None,
+            bell: args.bell,
+            ring: false,
+            steady: args.steady,
+            launch_budget: 0.0,
+            idle_frames: args
+                .idle_secs
+                .map(|secs| (secs * STEPS_PER_SECOND).round() as u64),
+            last_input_frame: 0,
+            liveliness: 0.0,
+            spread: args.spread,
+            finale_frames: 0,
+            paused: false,
+            input_mode: InputMode::Show,
+            last_launch_color: Color::from_rgb(255, 0, 0),
+            time_scale: 1.0,
+            steps_per_update: STEPS_PER_SECOND / args.fps as f64,
+            step_budget: 0.0,
+            params: SimParams {
+                gravity: args.gravity,
+                drag: args.drag,
+                wind: args.wind,
+                boundary: args.boundary,
+                ground: args.ground,
+                spawn_chance: args.density,
+                ..SimParams::default()
+            },
+            place_attractors: args.attractors,
+            attractors: vec![],
+            spark_size: args.spark_size,
+            trail_length: args.trail,
+            multistage: args.multistage,
+            crackle: args.crackle,
+            cooling: args.cooling,
+            twinkle: args.twinkle,
+            mortar_frames: if args.mortar { args.mortar_frames } else { 0 },
+            tinted_rockets: args.tinted_rockets,
+            launch_trail: args.launch_trail,
+            mirror: args.mirror,
+            gravity_jitter: args.gravity_jitter,
+            spread_angle: args.spread_angle,
+            rise: args.rise,
+            dud_rate: args.dud_rate,
+            particles_per_firework: args.particles_per_firework,
+            presets: args.presets.clone(),
+            shape: args.shape.as_ref().map(|shape| shape.points().into()),
+            rocket_size: (args.rocket_width, args.rocket_height),
+            spark_density: args.spark_density,
+            background: args.background.unwrap_or(Color::from_rgb(0, 0, 0)),
+            horizon: args.horizon,
+            kinds: args.kinds.clone(),
+            invert: args.invert,
+            additive: args.additive,
+            exposure: args.long_exposure,
+            unexposed_steps: 0,
+            fade_curve: args.fade_curve,
+            motion_blur: args.motion_blur.then_some(args.blur_speed),
+            smooth: args.smooth,
+            palette: args.palette.clone(),
+            color_count: args.colors,
+            anchor_colors: vec![],
+            chemistry: args.chemistry,
+            element: args.element,
+            rainbow_step: args.rainbow.then_some(args.rainbow_step),
+            hue_drift: (args.hue_drift != 0.0).then_some(args.hue_drift / STEPS_PER_SECOND),
+            base_hue: 0.0,
+            hue_cursor: 0.0,
+            flash_core: args.flash_core,
+            two_tone: args.two_tone,
+            vary_size: args.vary_size,
+            min_spark_frames: args.min_spark_frames,
+            max_age: args.max_age,
+            depth_fade: args.depth,
+            script: vec![],
+            beats: None,
+            audio_reactive: args.audio_reactive,
+            next_launch: 0,
+            loop_script: args.loop_script,
+            script_start: 0,
+            frame: 0,
+            max_fireworks: args.max_fireworks,
+            show_stats: false,
+            // ANSI output has no terminal to position the status text in.
+            status_bar: args.status_bar && !args.ascii,
+            fps: 0.0,
+            overlay: TextOverlay::new(),
+            summary: RunSummary::default(),
+            recorder: None,
+            screenshot_requested: false,
+            messages: vec![],
+            notice: None,
+            replay: None,
+            playback: None,
+            updates: 0,
+            duration: args.duration.map(Duration::from_secs_f64),
+            elapsed: Duration::ZERO,
+            quitting: None,
+            finished: false,
+        }
+    }
+
+    /// Swaps the `input` of the next update for the one played back, and
+    /// adds it to the replay being recorded.
+    fn replay_input(&mut self, input: &mut TerminalInputState) {
+        if let Some(ref mut playback) = self.playback {
+            input.replay(&playback.events(self.updates));
+        }
+        if let Some(ref mut replay) = self.replay {
+            let update = self.updates;
+            replay
+                .events
+                .extend(input.events().into_iter().map(|event| (update, event)));
+        }
+        self.updates += 1;
+    }
+
+    /// Volumes of the beats heard during the current update, played back
+    /// instead while playing back a replay, and added to the replay being
+    /// recorded.
+    fn heard_beats(&mut self) -> Vec<f64> {
+        // The update was already counted when its input was replayed.
+        let update = self.updates.saturating_sub(1);
+        let volumes = match self.playback {
+            Some(ref mut playback) => playback.beats(update),
+            None => self
+                .beats
+                .as_ref()
+                .map_or(vec![], |beats| beats.try_iter().collect()),
+        };
+        if let Some(ref mut replay) = self.replay {
+            replay
+                .beats
+                .extend(volumes.iter().map(|&volume| (update, volume)));
+        }
+        volumes
+    }
+
+    /// Finalizes the GIF file, if recording.
+    fn finish_recording(&mut self) -> Result<()> {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Picks the color of a new firework by the first of the color picker,
+    /// the element, the metal salts, the color wheel, the palette and the
+    /// shared random colors which is configured, or a completely random one
+    /// without any.
+    fn launch_color<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Color {
+        if let InputMode::ColorPicker { hue, lightness } = self.input_mode {
+            return HslColor::new(hue, PICKER_SATURATION, lightness).into();
+        }
+        if let Some(element) = self.element {
+            return element.color();
+        }
+        if self.chemistry {
+            return chemistry::chemistry_color(rng);
+        }
+        if let Some(step) = self.rainbow_step {
+            let hue = self.hue_cursor;
+            self.hue_cursor = (hue + step).rem_euclid(360.0);
+            return HslColor::new(hue, RAINBOW_SATURATION, RAINBOW_LIGHTNESS).into();
+        }
+        if let Some(ref palette) = self.palette {
+            return self.drift(palette[rng.gen_range(0..palette.len())]);
+        }
+        let random_color =
+            |rng: &mut R| Color::from_rgb(rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>());
+        match self.color_count {
+            Some(count) => {
+                if self.anchor_colors.is_empty() {
+                    self.anchor_colors = (0..count).map(|_| random_color(rng)).collect();
+                }
+                self.drift(self.anchor_colors[rng.gen_range(0..count)])
+            }
+            None if self.hue_drift.is_some() => {
+                let hue = self.base_hue + rng.gen_range(-DRIFT_HUE_RANGE..=DRIFT_HUE_RANGE);
+                let saturation = rng.gen_range(DRIFT_SATURATION);
+                HslColor::new(hue.rem_euclid(360.0), saturation, DRIFT_LIGHTNESS).into()
+            }
+            None => random_color(rng),
+        }
+    }
+
+    /// Turns the hue of `color` by the drifted base hue, if drifting.
+    fn drift(&self, color: Color) -> Color {
+        if self.hue_drift.is_none() {
+            return color;
+        }
+        let hsl = color.as_hsl();
+        HslColor::new((hsl.h + self.base_hue).rem_euclid(360.0), hsl.s, hsl.l).into()
+    }
+
+    /// Picks the kind of a random launch among the configured ones.
+    fn launch_kind<R: Rng + ?Sized>(&self, rng: &mut R) -> FireworkKind {
+        match self.kinds[..] {
+            [] => rng.gen(),
+            ref kinds => kinds[rng.gen_range(0..kinds.len())],
+        }
+    }
+
+    /// Picks the column of a random launch on a canvas `width` pixels wide,
+    /// clustered around the center by the configured spread. The `width`
+    /// must not be 0.
+    fn launch_column<R: Rng + ?Sized>(&self, rng: &mut R, width: u32) -> i64 {
+        // Blends from a triangular distribution peaking at the center into a
+        // uniform one, while widening from the center to the full width.
+        let uniform = rng.gen::<f64>() * 2.0 - 1.0;
+        let triangular = rng.gen::<f64>() + rng.gen::<f64>() - 1.0;
+        let offset = self.spread * (self.spread * uniform + (1.0 - self.spread) * triangular);
+        let x = (width as f64 / 2.0 * (1.0 + offset)).floor() as i64;
+        x.clamp(0, width as i64 - 1)
+    }
+
+    /// Launches a firework of random kind with the configured look for a
+    /// canvas of `area` pixels, unless there already are too many or the show
+    /// is ending.
+    fn launch<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        x: i64,
+        y: i64,
+        speed: (f64, f64),
+        area: u32,
+        color: Color,
+    ) {
+        if !self.may_launch() {
+            return;
+        }
+        let launch = replay::Launch {
+            frame: self.frame,
+            kind: self.launch_kind(rng),
+            x,
+            y,
+            speed,
+            color,
+            inner_color: self.inner_color(rng),
+            size: None,
+            seed: rng.gen(),
+        };
+        self.fire(launch, area);
+    }
+
+    /// Launches the preset of the number key at `index` from a random column
+    /// of a canvas of `width` x `height` pixels, unless there already are
+    /// too many fireworks or the show is ending.
+    fn launch_preset<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        index: usize,
+        width: u32,
+        height: u32,
+    ) {
+        if width == 0 || !self.may_launch() {
+            return;
+        }
+        let preset = self.presets[index];
+        let launch = replay::Launch {
+            frame: self.frame,
+            kind: preset.kind,
+            x: self.launch_column(rng, width),
+            y: height as i64,
+            speed: random_launch_speed(rng),
+            color: preset.color,
+            inner_color: self.inner_color(rng),
+            size: Some((preset.size, preset.sparks)),
+            seed: rng.gen(),
+        };
+        self.fire(launch, width * height);
+    }
+
+    /// Launches a firework for a beat of `volume` from a random column of a
+    /// canvas of `width` x `height` pixels, louder beats bursting brighter
+    /// and larger.
+    fn launch_beat<R: Rng + ?Sized>(&mut self, rng: &mut R, volume: f64, width: u32, height: u32) {
+        if width == 0 || !self.may_launch() {
+            return;
+        }
+        let volume = volume.clamp(0.0, 1.0);
+        let scale =
+            |range: &RangeInclusive<f64>| range.start() + (range.end() - range.start()) * volume;
+        let brightness = BEAT_MIN_BRIGHTNESS + (1.0 - BEAT_MIN_BRIGHTNESS) * volume;
+        let color = self.launch_color(rng);
+        let launch = replay::Launch {
+            frame: self.frame,
+            kind: self.launch_kind(rng),
+            x: self.launch_column(rng, width),
+            y: height as i64,
+            speed: random_launch_speed(rng),
+            color: Color::blend(&Color::from_rgb(0, 0, 0), &color, brightness),
+            inner_color: self.inner_color(rng),
+            size: Some((scale(&BEAT_SIZES), scale(&BEAT_SPARKS))),
+            seed: rng.gen(),
+        };
+        self.fire(launch, width * height);
+    }
+
+    /// Whether another firework can be launched, which it can't with too
+    /// many in flight, once the show is ending or while playing back a
+    /// replay, which only fires the recorded launches.
+    fn may_launch(&self) -> bool {
+        // Mirrored fireworks come with their reflection, but a pair always
+        // fits on its own.
+        let copies = if self.mirror { 2 } else { 1 };
+        self.firework_count() + copies <= self.max_fireworks.max(copies)
+            && self.quitting.is_none()
+            && self.playback.is_none()
+    }
+
+    /// Color of the inner sparks of a new firework, if it has two tones.
+    fn inner_color<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<Color> {
+        self.two_tone.then(|| self.launch_color(rng))
+    }
+
+    /// Fireworks in flight, including the reflections of mirrored ones.
+    fn firework_count(&self) -> usize {
+        self.fireworks.iter().map(Firework::copies).sum()
+    }
+
+    /// Fires the launches of the replay played back due by the current
+    /// step, for a canvas of `area` pixels.
+    fn fire_played_back(&mut self, area: u32) {
+        while let Some(launch) = self
+            .playback
+            .as_mut()
+            .and_then(|playback| playback.next_launch(self.frame))
+        {
+            self.fire(launch, area);
+        }
+    }
+
+    /// Adds the firework of `launch` with the configured look for a canvas of
+    /// `area` pixels, see [State::launch], also adding it to the replay being
+    /// recorded.
+    ///
+    /// Everything random about the firework comes from the seed of the
+    /// launch, so played back ones burst just like the recorded ones.
+    fn fire(&mut self, launch: replay::Launch, area: u32) {
+        if let Some(ref mut replay) = self.replay {
+            replay.launches.push(replay::Launch {
+                frame: self.frame,
+                ..launch
+            });
+        }
+        let replay::Launch {
+            kind,
+            x,
+            y,
+            speed,
+            color,
+            inner_color,
+            size,
+            seed,
+            ..
+        } = launch;
+        self.summary.fireworks_launched += 1;
+        self.last_launch_color = color;
+        let rng = &mut Xoshiro256PlusPlus::seed_from_u64(seed);
+        // `--dud-rate 0` skips the roll, leaving the shows of existing seeds
+        // untouched.
+        let dud = (self.dud_rate > 0.0 && rng.gen_bool(self.dud_rate)).then(|| {
+            if rng.gen() {
+                Dud::Fizzle
+            } else {
+                Dud::Weak
+            }
+        });
+        self.fireworks.push(
+            Firework::new(rng, &mut self.pool, kind, x, y, speed, color)
+                .with_spark_size(self.spark_size)
+                .with_trail(self.trail_length)
+                .with_multistage(self.multistage)
+                .with_crackle(self.crackle)
+                .with_cooling(self.cooling)
+                .with_twinkle(self.twinkle)
+                .with_core(self.flash_core)
+                .with_inner_color(inner_color)
+                .with_vary_size(self.vary_size)
+                .with_min_spark_frames(self.min_spark_frames)
+                .with_max_age(self.max_age)
+                .with_depth_fade(self.depth_fade)
+                .with_mortar(self.mortar_frames)
+                .with_tinted_rocket(self.tinted_rockets)
+                .with_launch_trail(self.launch_trail)
+                .with_shape(self.shape.clone())
+                .with_mirror(self.mirror)
+                .with_gravity_jitter(self.gravity_jitter)
+                .with_spread(self.spread_angle)
+                .with_rise(self.rise)
+                .with_dud(dud)
+                .with_size(size)
+                .with_max_particles(self.particles_per_firework)
+                .with_rocket_size(self.rocket_size.0, self.rocket_size.1)
+                .with_canvas_area(area, self.spark_density),
+        );
+    }
+
+    /// Launches a firework from a random column at the bottom of a canvas of
+    /// `width` x `height` pixels, unless it has no columns at all.
+    fn launch_random<R: Rng + ?Sized>(&mut self, rng: &mut R, width: u32, height: u32) {
+        if width == 0 || !self.may_launch() {
+            return;
+        }
+        let x = self.launch_column(rng, width);
+        let speed = random_launch_speed(rng);
+        let color = self.launch_color(rng);
+        self.launch(rng, x, height as i64, speed, width * height, color);
+    }
+
+    /// Clears the canvas to the sky and draws all fireworks on it.
+    ///
+    /// With a long exposure the previous frame is faded by the steps taken
+    /// since instead, and the fireworks add their light on top of it.
+    fn draw<C: Canvas>(&mut self, canvas: &mut C) {
+        // Launched between steps when recorded, so already drawn before the
+        // next one.
+        self.fire_played_back(canvas.width() * canvas.height());
+        // Inverted shows are drawn like on a dark sky and only mirrored at
+        // the end, so a long exposure first mirrors the last frame back.
+        let sky = Sky {
+            top: self.background,
+            horizon: self.horizon.unwrap_or(self.background),
+        };
+        let sky = if self.invert {
+            if self.exposure.is_some() {
+                canvas::mirror_lightness(canvas);
+            }
+            sky.mirror_lightness()
+        } else {
+            sky
+        };
+        match self.exposure {
+            Some(decay) => {
+                let brightness = decay.powi(self.unexposed_steps as i32);
+                canvas::fade(canvas, &sky, brightness);
+                self.unexposed_steps = 0;
+            }
+            None => canvas::fill_sky(canvas, &sky),
+        }
+        let style = DrawStyle {
+            sky,
+            additive: self.additive || self.exposure.is_some(),
+            fade_curve: self.fade_curve,
+            motion_blur: self.motion_blur,
+            smooth: self.smooth,
+        };
+        for star in self.stars.iter() {
+            star.draw(canvas, &sky, self.frame);
+        }
+        for comet in self.comets.iter() {
+            comet.draw(canvas, &style);
+        }
+        for glow in self.glows.iter() {
+            glow.draw(canvas);
+        }
+        for shockwave in self.shockwaves.iter() {
+            shockwave.draw(canvas, &sky);
+        }
+        for firework in self.fireworks.iter() {
+            firework.draw(canvas, &style);
+        }
+        if let Some(left) = self.quitting {
+            canvas::darken(canvas, left as f64 / QUIT_FADE_FRAMES as f64);
+        }
+        if self.invert {
+            canvas::mirror_lightness(canvas);
+        }
+    }
+
+    /// Canvas and physics of the last simulation step.
+    pub fn sim_params(&self) -> SimParams {
+        self.params
+    }
+
+    /// Counts of what is currently in the sky.
+    pub fn stats(&self) -> SceneStats {
+        let fireworks = self.fireworks.iter();
+        SceneStats {
+            fireworks: self.firework_count(),
+            particles: fireworks
+                .clone()
+                .map(|firework| firework.total_particles() * firework.copies())
+                .sum(),
+            dead: fireworks
+                .filter(|firework| firework.is_dead())
+                .map(Firework::copies)
+                .sum(),
+        }
+    }
+
+    /// Terminal rows the canvas covers in a terminal of `rows` rows.
+    fn canvas_rows(&self, rows: u16) -> u16 {
+        if self.status_bar {
+            rows.saturating_sub(1)
+        } else {
+            rows
+        }
+    }
+
+    /// Opens the color picker at the color of the latest launch, or closes it
+    /// again.
+    fn toggle_color_picker(&mut self) {
+        self.input_mode = match self.input_mode {
+            InputMode::Show => {
+                let color = self.last_launch_color.as_hsl();
+                InputMode::ColorPicker {
+                    hue: color.h,
+                    lightness: color
+                        .l
+                        .clamp(*PICKER_LIGHTNESS.start(), *PICKER_LIGHTNESS.end()),
+                }
+            }
+            InputMode::ColorPicker { .. } => InputMode::Show,
+        };
+    }
+
+    /// Removes every firework, glow, shockwave and comet from the sky at
+    /// once.
+    fn clear(&mut self) {
+        for firework in self.fireworks.drain(..) {
+            firework.recycle(&mut self.pool);
+        }
+        self.glows.clear();
+        self.shockwaves.clear();
+        self.comets.clear();
+        self.attractors.clear();
+        self.finale_frames = 0;
+    }
+
+    /// Advances the simulation by a single step under the current
+    /// [SimParams].
+    fn step<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let SimParams { width, height, .. } = self.params;
+        for firework in self.fireworks.extract_if(.., |firework| firework.is_dead()) {
+            firework.recycle(&mut self.pool);
+        }
+        let sky_rng = self
+            .sky_rng
+            .get_or_insert_with(|| Xoshiro256PlusPlus::seed_from_u64(rng.gen()));
+        if self.stars.len() < self.star_count {
+            // From the seeded random numbers, so the sky is the same for the
+            // same seed.
+            self.stars = (0..self.star_count).map(|_| Star::new(sky_rng)).collect();
+        }
+
+        self.fire_played_back(width * height);
+        while let Some(launch) = self.script.get(self.next_launch).copied() {
+            if launch.frame + self.script_start > self.frame {
+                break;
+            }
+            self.next_launch += 1;
+            if self.loop_script && self.next_launch == self.script.len() {
+                // The next round starts the step after the last launch, any
+                // fireworks still in flight simply burn out.
+                self.next_launch = 0;
+                self.script_start += launch.frame + 1;
+            }
+            let y_speed =
+                Firework::launch_speed(height as f64 - launch.y as f64, self.params.gravity);
+            let x = launch.x as i64;
+            let speed = (0.0, y_speed);
+            self.launch(rng, x, height as i64, speed, width * height, launch.color);
+        }
+        self.unexposed_steps += 1;
+        if let Some(drift) = self.hue_drift {
+            self.base_hue = (self.base_hue + drift).rem_euclid(360.0);
+        }
+
+        if let Some(idle_frames) = self.idle_frames {
+            let idle = self.frame + 1 - self.last_input_frame > idle_frames;
+            let ramp = 1.0 / (IDLE_RAMP_SECS * STEPS_PER_SECOND);
+            self.liveliness = if idle {
+                (self.liveliness + ramp).min(1.0)
+            } else {
+                (self.liveliness - ramp).max(0.0)
+            };
+        }
+
+        let spawn_chance = if self.finale_frames > 0 {
+            self.finale_frames -= 1;
+            FINALE_SPAWN_CHANCE
+        } else if self.script.is_empty() && !self.audio_reactive {
+            let chance = self.params.spawn_chance;
+            let idle_chance = IDLE_SPAWN_CHANCE.max(chance);
+            chance + (idle_chance - chance) * self.liveliness
+        } else {
+            // Scripted and audio reactive shows only launch randomly during
+            // a finale.
+            0.0
+        };
+        if self.steady {
+            // Launches as soon as a whole one is due, evenly spaced at the
+            // same average rate.
+            self.launch_budget += spawn_chance;
+            while self.launch_budget >= 1.0 {
+                self.launch_budget -= 1.0;
+                self.launch_random(rng, width, height);
+            }
+        } else if rng.gen::<f64>() < spawn_chance {
+            self.launch_random(rng, width, height);
+        }
+
+        let mut bursts = vec![];
+        for firework in self.fireworks.iter_mut() {
+            firework.update(&mut self.pool, &self.params, &self.attractors, &mut bursts);
+            firework.kill_outside(width as f64, height as f64);
+        }
+
+        if self.glow {
+            self.glows.retain(|glow| !glow.is_dead());
+            for glow in self.glows.iter_mut() {
+                glow.update();
+            }
+            self.glows.extend(Glow::merged(&bursts, width, height));
+        }
+        if self.shockwave {
+            self.shockwaves.retain(|shockwave| !shockwave.is_dead());
+            for shockwave in self.shockwaves.iter_mut() {
+                shockwave.update();
+            }
+            self.shockwaves.extend(bursts.iter().map(Shockwave::new));
+        }
+        self.ring |= self.bell && !bursts.is_empty();
+
+        let (width, height) = (width as f64, height as f64);
+        self.comets.retain(|comet| !comet.is_dead(width, height));
+        for comet in self.comets.iter_mut() {
+            comet.update();
+        }
+        let sky_rng = self.sky_rng.as_mut().expect("seeded above");
+        if self.comet_chance > 0.0 && sky_rng.gen_bool(self.comet_chance) {
+            self.comets.push(Comet::new(sky_rng, width as u32));
+        }
+        // Launches of this step count as launched at its start.
+        self.frame += 1;
+    }
+}
+
+/// Speed of a randomly launched rocket, drifting slightly to either side.
+fn random_launch_speed<R: Rng + ?Sized>(rng: &mut R) -> (f64, f64) {
+    (
+        MAX_LAUNCH_DRIFT * (rng.gen::<f64>() * 2.0 - 1.0),
+        -1.0 - rng.gen::<f64>(),
+    )
+}
+
+/// Advances the show by a single simulation step, moving every firework and
+/// launching new ones, without touching any input or canvas.
+pub fn tick<R: Rng + ?Sized>(state: &mut State, rng: &mut R, params: &SimParams) {
+    state.params = *params;
+    state.step(rng);
+}
+
+/// Draws the sky and everything in it onto `canvas`, leaving out overlays
+/// like the stats line.
+pub fn draw_scene<C: Canvas>(state: &mut State, canvas: &mut C) {
+    state.draw(canvas);
+}
+
+fn time_based_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+/// Runs the show as configured by the command line arguments.
+pub fn run_cli() -> Result<()> {
+    let mut args = cli::Args::parse()?;
+    if !args.ascii && args.bench.is_none() && !std::io::stdout().is_tty() {
+        // Drawing into a file or pipe only works as text.
+        eprintln!("stdout is not a terminal, writing the frames as text like with --ascii");
+        args.ascii = true;
+    }
+    if args.background.is_none() && !args.ascii && args.bench.is_none() {
+        args.background = terminal_theme::background(terminal_theme::QUERY_TIMEOUT)
+            // Inverted shows are meant for light terminals.
+            .filter(|color| !args.invert || color.as_hsl().l > 50.0);
+    }
+    if args.invert && args.background.is_none() {
+        args.background = Some(Color::from_rgb(255, 255, 255));
+    }
+    let playback = args.play_replay.as_deref().map(Replay::load).transpose()?;
+    let seed = match playback {
+        Some(ref replay) => replay.seed,
+        None => args.seed.unwrap_or_else(time_based_seed),
+    };
+    eprintln!("seed: {seed}");
+
+    let mut state = State::new(&args);
+    state.playback = playback.map(Playback::new);
+    if let Some(ref path) = args.script {
+        state.script = script::load(path)?;
+    }
+    if args.bench.is_some() {
+        state.audio_reactive = false;
+    } else if args.audio_reactive && state.playback.is_none() {
+        // Played back shows take their beats from the replay instead.
+        match audio::listen(args.audio_threshold) {
+            Ok(beats) => state.beats = Some(beats),
+            Err(error) => {
+                eprintln!("no audio input, launching randomly instead: {error:#}");
+                state.audio_reactive = false;
+            }
+        }
+    }
+    if let Some(frames) = args.bench {
+        let (columns, rows) = terminal::size().unwrap_or((80, 24));
+        bench::run(state, seed, frames, columns, rows)
+    } else if args.ascii {
+        // The output may not go to a terminal at all, so it gets a common
+        // terminal size when there is none.
+        let (columns, rows) = terminal::size().unwrap_or((80, 24));
+        run::<AnsiCanvas>(
+            &args,
+            seed,
+            state,
+            TerminalInputState::detached(),
+            columns,
+            rows,
+        )
+    } else if args.no_diff {
+        let (columns, rows) = terminal::size()?;
+        run::<RedrawCanvas>(&args, seed, state, TerminalInputState::new(), columns, rows)
+    } else {
+        let (columns, rows) = terminal::size()?;
+        run::<CrosstermCanvas>(&args, seed, state, TerminalInputState::new(), columns, rows)
+    }
+}
+
+/// Runs the show on a canvas of `columns` x `rows` terminal cells.
+fn run<C: ShowCanvas>(
+    args: &cli::Args,
+    seed: u64,
+    mut state: State,
+    mut input: TerminalInputState,
+    columns: u16,
+    rows: u16,
+) -> Result<()> {
+    // Played back shows start out as large as when they were recorded.
+    let (columns, rows) = state
+        .playback
+        .as_ref()
+        .map_or((columns, rows), Playback::size);
+    if args.record_replay.is_some() {
+        state.replay = Some(Replay::new(seed, (columns, rows)));
+    }
+    let mut canvas = C::for_cells(columns, state.canvas_rows(rows));
+    if let Some(ref path) = args.record {
+        state.recorder = Some(GifRecorder::create(
+            path,
+            canvas.width() as u16,
+            canvas.height() as u16,
+            args.record_frames,
+        )?);
+    }
+
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+    input.restore_on_panic();
+    input.begin()?;
+    let result = main_loop(args.fps, &mut rng, &mut state, &mut input, &mut canvas);
+    // The terminal is restored even when the show failed.
+    input.finish()?;
+    canvas.finish()?;
+    for message in &state.messages {
+        eprintln!("{message}");
+    }
+    state.finish_recording()?;
+    if let Some(ref path) = args.stats_json {
+        state.summary.finish(state.elapsed);
+        state.summary.write(path)?;
+    }
+    if let (Some(path), Some(replay)) = (&args.record_replay, &state.replay) {
+        replay.write(path)?;
+    }
+    result?;
+    if input.interrupted() {
+        // SIGINT exit code, as if Ctrl-C had stopped the program.
+        std::process::exit(130);
+    }
+    Ok(())
+}
+
+/// Calls `update` `fps` times per second and renders as often as the canvas
+/// allows in between, the same way `pixel_loop::run` does, until the show is
+/// finished.
+fn main_loop<R: Rng + ?Sized, C: ShowCanvas>(
+    fps: u32,
+    rng: &mut R,
+    state: &mut State,
+    input: &mut TerminalInputState,
+    canvas: &mut C,
+) -> Result<()> {
+    let timestep = Duration::from_secs(1) / fps;
+    let mut accumulator = Duration::ZERO;
+    let mut last_time = Instant::now();
+    while !state.finished {
+        let now = Instant::now();
+        // Long stalls are not caught up on.
+        let dt = (now - last_time).min(Duration::from_millis(100));
+        last_time = now;
+
+        while accumulator > timestep && !state.finished {
+            input.next_loop()?;
+            state.replay_input(input);
+            update(rng, state, input, canvas)?;
+            accumulator -= timestep;
+        }
+        render(state, canvas, dt)?;
+        accumulator += dt;
+    }
+    Ok(())
+}
+
+/// Advances the show by one update, taking all randomness from `rng`.
+fn update<R: Rng + ?Sized, C: ShowCanvas>(
+    rng: &mut R,
+    state: &mut State,
+    input: &TerminalInputState,
+    canvas: &mut C,
+) -> Result<()> {
+    if let Some((columns, rows)) = input.resized() {
+        // The canvas can't be resized in place, a fresh one redraws the whole
+        // terminal on the next render.
+        execute!(std::io::stdout(), terminal::Clear(ClearType::All))?;
+        *canvas = C::for_cells(columns, state.canvas_rows(rows));
+        let (width, height) = (canvas.width() as f64, canvas.height() as f64);
+        state
+            .fireworks
+            .retain(|firework| firework.is_within(width, height));
+    }
+
+    if input.interrupted() {
+        state.finished = true;
+        return Ok(());
+    }
+
+    if let Some(ref mut left) = state.quitting {
+        *left = left.saturating_sub(1);
+        state.finished = *left == 0;
+        // Paused shows only fade out.
+        if state.paused {
+            return Ok(());
+        }
+    } else if input.is_key_pressed(KeyboardKey::Q)
+        || state
+            .duration
+            .is_some_and(|duration| state.elapsed >= duration)
+    {
+        state.quitting = Some(QUIT_FADE_FRAMES);
+    }
+
+    if input.has_input() {
+        state.last_input_frame = state.frame;
+    }
+
+    if input.is_key_pressed(KeyboardKey::F) {
+        state.show_stats = !state.show_stats;
+    }
+
+    if input.is_key_pressed(KeyboardKey::P) {
+        state.screenshot_requested = true;
+    }
+
+    // Also works while paused, the next render already draws the empty sky.
+    if input.is_key_pressed(KeyboardKey::R) {
+        state.clear();
+    }
+
+    if input.is_key_pressed(KeyboardKey::Space) {
+        state.paused = !state.paused;
+    }
+
+    if input.is_key_pressed(KeyboardKey::Equal) {
+        state.time_scale = (state.time_scale * TIME_SCALE_FACTOR).min(*TIME_SCALES.end());
+    }
+    if input.is_key_pressed(KeyboardKey::Minus) {
+        state.time_scale = (state.time_scale / TIME_SCALE_FACTOR).max(*TIME_SCALES.start());
+    }
+    if input.is_key_pressed(KeyboardKey::Zero) {
+        state.time_scale = 1.0;
+    }
+
+    if input.is_key_pressed(KeyboardKey::C) {
+        state.toggle_color_picker();
+    }
+    if let InputMode::ColorPicker {
+        ref mut hue,
+        ref mut lightness,
+    } = state.input_mode
+    {
+        if input.is_key_pressed(KeyboardKey::Left) {
+            *hue = (*hue - PICKER_HUE_STEP).rem_euclid(360.0);
+        }
+        if input.is_key_pressed(KeyboardKey::Right) {
+            *hue = (*hue + PICKER_HUE_STEP).rem_euclid(360.0);
+        }
+        if input.is_key_pressed(KeyboardKey::Up) {
+            *lightness = (*lightness + PICKER_LIGHTNESS_STEP).min(*PICKER_LIGHTNESS.end());
+        }
+        if input.is_key_pressed(KeyboardKey::Down) {
+            *lightness = (*lightness - PICKER_LIGHTNESS_STEP).max(*PICKER_LIGHTNESS.start());
+        }
+    }
+
+    // Beats heard while paused are dropped instead of all launching at once
+    // afterwards.
+    let volumes = state.heard_beats();
+
+    if state.paused {
+        return Ok(());
+    }
+
+    for volume in volumes {
+        state.launch_beat(rng, volume, canvas.width(), canvas.height());
+    }
+
+    if input.is_key_pressed(KeyboardKey::Enter) {
+        state.finale_frames = FINALE_FRAMES;
+    }
+
+    for (index, key) in PRESET_KEYS.into_iter().enumerate() {
+        if index < state.presets.len() && input.is_key_pressed(key) {
+            state.launch_preset(rng, index, canvas.width(), canvas.height());
+        }
+    }
+
+    // The arrow keys pick the color while the color picker is open.
+    if state.input_mode == InputMode::Show {
+        if input.is_key_pressed(KeyboardKey::Left) {
+            state.params.wind = (state.params.wind - WIND_STEP).max(-MAX_WIND);
+        }
+        if input.is_key_pressed(KeyboardKey::Right) {
+            state.params.wind = (state.params.wind + WIND_STEP).min(MAX_WIND);
+        }
+    }
+
+    for click in input.mouse_clicks() {
+        if click.button == MouseButton::Right && state.place_attractors {
+            if state.attractors.len() == MAX_ATTRACTORS {
+                state.attractors.remove(0);
+            }
+            state.attractors.push(Attractor {
+                position: (click.column as f64, click.row as f64 * 2.0),
+                strength: ATTRACTOR_STRENGTH,
+            });
+            continue;
+        }
+        if click.button != MouseButton::Left {
+            continue;
+        }
+        // Every terminal row holds two canvas pixels.
+        if !state.may_launch() {
+            continue;
+        }
+        let target_y = click.row as f64 * 2.0;
+        let color = state.launch_color(rng);
+        state.launch(
+            rng,
+            click.column as i64,
+            canvas.height() as i64,
+            (
+                0.0,
+                Firework::launch_speed(canvas.height() as f64 - target_y, state.params.gravity),
+            ),
+            canvas.width() * canvas.height(),
+            color,
+        );
+    }
+
+    // Update rates, slow motion and fast forward all take whole simulation
+    // steps: every update adds its share of steps to a budget, and each full
+    // unit in it is one step. Fewer steps than updates thereby skip updates
+    // instead of changing the physics.
+    state.step_budget += state.steps_per_update * state.time_scale;
+    while state.step_budget >= 1.0 {
+        state.step_budget -= 1.0;
+        let params = SimParams {
+            width: canvas.width(),
+            height: canvas.height(),
+            ..state.params
+        };
+        tick(state, rng, &params);
+    }
+    Ok(())
+}
+
+fn render<C: ShowCanvas>(state: &mut State, canvas: &mut C, dt: Duration) -> Result<()> {
+    draw_scene(state, canvas);
+
+    if let Some(ref mut recorder) = state.recorder {
+        if !recorder.capture(canvas, dt)? {
+            state.finish_recording()?;
+        }
+    }
+
+    if state.screenshot_requested {
+        state.screenshot_requested = false;
+        let path = screenshot::timestamped_path();
+        // A failed screenshot isn't worth ending the show for.
+        let message = match screenshot::save(canvas, &path) {
+            Ok(()) => format!("saved screenshot `{}`", path.display()),
+            Err(error) => format!("error: {error:#}"),
+        };
+        let until = state.elapsed + Duration::from_secs_f64(NOTICE_SECS);
+        state.notice = Some((message.clone(), until));
+        state.messages.push(message);
+    }
+
+    canvas.render()?;
+    if state.ring {
+        // Bursts of the same frame share a single ring.
+        state.ring = false;
+        let mut stdout = std::io::stdout();
+        stdout.write_all(b"\x07")?;
+        stdout.flush()?;
+    }
+
+    state.elapsed += dt;
+    state.summary.frames_rendered += 1;
+    state.summary.peak_particles = state.summary.peak_particles.max(state.stats().particles);
+    if dt > Duration::ZERO {
+        state.fps = state.fps * 0.9 + 0.1 / dt.as_secs_f64();
+    }
+    if matches!(state.notice, Some((_, until)) if until <= state.elapsed) {
+        state.notice = None;
+    }
+    let notice = state.notice.as_ref().map(|(text, _)| text.as_str());
+    let mut lines = vec![];
+    if state.show_stats {
+        let stats = state.stats();
+        lines.push(TextLine {
+            column: 0,
+            row: 0,
+            background: None,
+            text: format!(
+                "FPS {:.0} | speed {:.2}x | fireworks {} | particles {}",
+                state.fps, state.time_scale, stats.fireworks, stats.particles
+            ),
+        });
+    }
+    if state.status_bar {
+        let wind = match state.params.wind {
+            wind if wind < 0.0 => '←',
+            wind if wind > 0.0 => '→',
+            _ => '·',
+        };
+        lines.push(TextLine {
+            column: 0,
+            // Right below the canvas, every row holds two of its pixels.
+            row: (canvas.height() / 2) as u16,
+            background: None,
+            text: format!(
+                "{} | density {:.2} | wind {wind} {:.3} | {:.0} fps{}",
+                if state.paused { "paused" } else { "playing" },
+                state.params.spawn_chance,
+                state.params.wind.abs(),
+                state.fps,
+                notice.map_or(String::new(), |notice| format!(" | {notice}"))
+            ),
+        });
+    } else if let Some(notice) = notice {
+        lines.push(TextLine {
+            column: 0,
+            row: (canvas.height() / 2).saturating_sub(1) as u16,
+            background: None,
+            text: notice.to_string(),
+        });
+    }
+    if let InputMode::ColorPicker { hue, lightness } = state.input_mode {
+        let row = if state.show_stats { 1 } else { 0 };
+        lines.push(TextLine {
+            column: 0,
+            row,
+            background: Some(HslColor::new(hue, PICKER_SATURATION, lightness).into()),
+            text: " ".repeat(PICKER_SWATCH_WIDTH),
+        });
+        lines.push(TextLine {
+            column: PICKER_SWATCH_WIDTH as u16,
+            row,
+            background: None,
+            text: format!(
+                " hue {hue:.0}° lightness {lightness:.0}% | ←/→ hue, ↑/↓ lightness, c close"
+            ),
+        });
+    }
+    state.overlay.show(canvas, &lines)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sim::NoopCanvas;
+
+    /// Runs `steps` simulation steps of a show seeded with 3, returning its
+    /// recorded replay and the pixels of every step.
+    ///
+    /// Every step after the first draws `skipped` more random numbers, as if
+    /// the code drew them differently.
+    fn record(playback: Option<Replay>, steps: usize, skipped: usize) -> (Replay, Vec<Vec<Color>>) {
+        let args = cli::Args {
+            dud_rate: 0.2,
+            ..cli::Args::default()
+        };
+        let mut state = State::new(&args);
+        state.replay = Some(Replay::new(3, (80, 24)));
+        state.playback = playback.map(Playback::new);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(3);
+        let mut canvas = NoopCanvas::new(80, 48);
+        let params = SimParams {
+            spawn_chance: 0.3,
+            ..state.sim_params()
+        };
+        let mut frames = vec![];
+        for step in 0..steps {
+            if step > 0 {
+                for _ in 0..skipped {
+                    rng.gen::<u64>();
+                }
+            }
+            tick(&mut state, &mut rng, &params);
+            if step % 50 == 0 {
+                state.launch_preset(&mut rng, 0, 80, 48);
+            }
+            draw_scene(&mut state, &mut canvas);
+            frames.push(canvas.pixels().to_vec());
+        }
+        (state.replay.unwrap(), frames)
+    }
+
+    /// Runs `steps` simulation steps of the show of `args` seeded with
+    /// `seed` on an 80 x 48 canvas.
+    fn run(args: &cli::Args, seed: u64, steps: usize) -> State {
+        let mut state = State::new(args);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+        let params = SimParams {
+            spawn_chance: 0.3,
+            ..state.sim_params()
+        };
+        for _ in 0..steps {
+            tick(&mut state, &mut rng, &params);
+        }
+        state
+    }
+
+    /// Horizontal positions of all particles in the sky.
+    fn columns(state: &State) -> Vec<f64> {
+        let positions = state.fireworks.iter().flat_map(Firework::positions);
+        positions.map(|(x, _)| x).collect()
+    }
+
+    #[test]
+    fn same_seed_same_show() {
+        let args = cli::Args::default();
+        let show = columns(&run(&args, 9, 300));
+        assert!(!show.is_empty());
+        assert_eq!(columns(&run(&args, 9, 300)), show);
+        assert_ne!(columns(&run(&args, 10, 300)), show);
+    }
+
+    #[test]
+    fn update_rate_keeps_the_physics() {
+        let second = |fps| {
+            let args = cli::Args {
+                fps,
+                ..cli::Args::default()
+            };
+            let mut state = State::new(&args);
+            let mut rng = Xoshiro256PlusPlus::seed_from_u64(4);
+            let mut input = TerminalInputState::detached();
+            let mut canvas = NoopCanvas::new(80, 48);
+            for _ in 0..fps {
+                state.replay_input(&mut input);
+                update(&mut rng, &mut state, &input, &mut canvas).unwrap();
+            }
+            assert_eq!(state.frame, STEPS_PER_SECOND as u64);
+            columns(&state)
+        };
+        let show = second(60);
+        assert!(!show.is_empty());
+        assert_eq!(second(30), show);
+    }
+
+    #[test]
+    fn max_fireworks_refuses_launches() {
+        let args = cli::Args {
+            max_fireworks: 1,
+            density: 0.0,
+            ..cli::Args::default()
+        };
+        let mut state = State::new(&args);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(5);
+        state.launch_random(&mut rng, 80, 48);
+        state.launch_random(&mut rng, 80, 48);
+        state.launch_preset(&mut rng, 0, 80, 48);
+        assert_eq!(state.fireworks.len(), 1);
+
+        let params = state.sim_params();
+        while !state.fireworks.iter().all(Firework::is_dead) {
+            tick(&mut state, &mut rng, &params);
+            state.launch_random(&mut rng, 80, 48);
+            assert!(state.fireworks.len() <= 1);
+        }
+        tick(&mut state, &mut rng, &params);
+        state.launch_random(&mut rng, 80, 48);
+        assert_eq!(state.fireworks.len(), 1);
+        assert!(!state.fireworks[0].is_dead());
+    }
+
+    #[test]
+    fn steady_launches_evenly() {
+        let args = cli::Args {
+            steady: true,
+            ..cli::Args::default()
+        };
+        let mut state = State::new(&args);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(6);
+        let params = SimParams {
+            spawn_chance: 0.25,
+            ..state.sim_params()
+        };
+        let mut launch_steps = vec![];
+        for step in 0..400 {
+            let launched = state.summary.fireworks_launched;
+            tick(&mut state, &mut rng, &params);
+            match state.summary.fireworks_launched - launched {
+                0 => {}
+                1 => launch_steps.push(step),
+                _ => panic!("several launches in step {step}"),
+            }
+        }
+        assert_eq!(launch_steps.len(), 100);
+        assert!(launch_steps.windows(2).all(|pair| pair[1] - pair[0] == 4));
+    }
+
+    #[test]
+    fn skips_launches_without_columns() {
+        let mut state = State::new(&cli::Args::default());
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(7);
+        state.launch_random(&mut rng, 0, 48);
+        state.launch_preset(&mut rng, 0, 0, 48);
+        state.launch_beat(&mut rng, 1.0, 0, 48);
+        let params = SimParams {
+            width: 0,
+            spawn_chance: 1.0,
+            ..state.sim_params()
+        };
+        for _ in 0..100 {
+            tick(&mut state, &mut rng, &params);
+        }
+        assert!(state.fireworks.is_empty());
+    }
+
+    #[test]
+    fn mirrored_fireworks_count_twice() {
+        let args = cli::Args {
+            mirror: true,
+            max_fireworks: 3,
+            ..cli::Args::default()
+        };
+        let mut state = State::new(&args);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(8);
+        for _ in 0..3 {
+            state.launch_random(&mut rng, 80, 48);
+        }
+        assert_eq!(state.fireworks.len(), 1);
+        let stats = state.stats();
+        assert_eq!((stats.fireworks, stats.particles), (2, 2));
+    }
+
+    #[test]
+    fn replays_reproduce_the_show() {
+        let (recorded, frames) = record(None, 400, 0);
+        assert!(recorded.launches.len() > 10);
+        for skipped in [0, 3] {
+            let (replayed, replayed_frames) = record(Some(recorded.clone()), 400, skipped);
+            assert_eq!(replayed.launches, recorded.launches);
+            assert!(replayed_frames == frames);
+        }
+    }
+
+    #[test]
+    fn space_launches_heavy_willows() {
+        let flags = theme::theme("space")
+            .unwrap()
+            .iter()
+            .map(|flag| flag.to_string());
+        let state = State::new(&cli::Args::default().apply(flags).unwrap());
+        assert!(state.sim_params().gravity > 2.0 * SimParams::DEFAULT.gravity);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(4);
+        assert!((0..50).all(|_| state.launch_kind(&mut rng) == FireworkKind::Willow));
+    }
+}
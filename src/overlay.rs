@@ -0,0 +1,106 @@
+//! Text drawn on top of the rendered canvas.
+//!
+//! The text is written straight to the terminal after the canvas has been
+//! rendered, so it never ends up in the canvas pixels.
+
+use anyhow::Result;
+use crossterm::cursor::{MoveTo, RestorePosition, SavePosition};
+use crossterm::queue;
+use crossterm::style::{self, Print, ResetColor, SetColors};
+use pixel_loop::canvas::Canvas;
+use pixel_loop::color::Color;
+use std::io::Write;
+
+const TEXT_COLOR: style::Color = style::Color::White;
+const TEXT_BACKGROUND: style::Color = style::Color::Black;
+
+/// A line of text at a terminal cell position.
+pub struct TextLine {
+    pub column: u16,
+    pub row: u16,
+    /// Color the text is written on instead of black.
+    pub background: Option<Color>,
+    pub text: String,
+}
+
+pub struct TextOverlay {
+    /// Cells covered by the previous frame's text as (column, row, width).
+    covered: Vec<(u16, u16, u16)>,
+}
+
+impl TextOverlay {
+    pub fn new() -> Self {
+        Self { covered: vec![] }
+    }
+
+    /// Replaces the text shown on top of the canvas.
+    ///
+    /// The canvas only redraws cells whose pixels changed, so cells covered by
+    /// the previous text are painted again from the canvas before the new
+    /// text is written.
+    pub fn show<C: Canvas>(&mut self, canvas: &C, lines: &[TextLine]) -> Result<()> {
+        if self.covered.is_empty() && lines.is_empty() {
+            return Ok(());
+        }
+
+        let mut buffer = Vec::new();
+        queue!(buffer, SavePosition)?;
+        for (column, row, width) in self.covered.drain(..) {
+            repaint_cells(&mut buffer, canvas, column, row, width)?;
+        }
+
+        for line in lines {
+            let available = (canvas.width() as usize).saturating_sub(line.column as usize);
+            let text: String = line.text.chars().take(available).collect();
+            queue!(
+                buffer,
+                MoveTo(line.column, line.row),
+                SetColors(style::Colors::new(
+                    TEXT_COLOR,
+                    line.background.as_ref().map_or(TEXT_BACKGROUND, rgb)
+                )),
+                Print(&text)
+            )?;
+            self.covered
+                .push((line.column, line.row, text.chars().count() as u16));
+        }
+        queue!(buffer, ResetColor, RestorePosition)?;
+
+        let mut stdout = std::io::stdout();
+        stdout.write_all(&buffer)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes the canvas pixels of a span of terminal cells as half blocks, the
+/// same way the canvas renders them.
+fn repaint_cells<W: Write, C: Canvas>(
+    writer: &mut W,
+    canvas: &C,
+    column: u16,
+    row: u16,
+    width: u16,
+) -> Result<()> {
+    let black = Color::from_rgb(0, 0, 0);
+    let y = row as i64 * 2;
+    queue!(writer, MoveTo(column, row))?;
+    for x in column as i64..(column + width) as i64 {
+        let upper = canvas.maybe_get(x, y).unwrap_or(&black);
+        let lower = canvas.maybe_get(x, y + 1).unwrap_or(&black);
+        queue!(
+            writer,
+            SetColors(style::Colors::new(rgb(upper), rgb(lower))),
+            Print("▀")
+        )?;
+    }
+    Ok(())
+}
+
+fn rgb(color: &Color) -> style::Color {
+    style::Color::Rgb {
+        r: color.r,
+        g: color.g,
+        b: color.b,
+    }
+}
@@ -0,0 +1,131 @@
+//! Curated color palettes for the launched fireworks.
+
+use anyhow::{bail, Context, Result};
+use pixel_loop::color::Color;
+use std::path::Path;
+
+use crate::color::FromHex;
+
+/// Names of all built-in palettes, as accepted by [palette].
+pub const PALETTE_NAMES: &[&str] = &[
+    "classic",
+    "patriotic",
+    "pastel",
+    "fire",
+    "cool",
+    "gold",
+    "neon",
+];
+
+/// Colors of the built-in palette called `name`.
+pub fn palette(name: &str) -> Option<Vec<Color>> {
+    let colors = match name {
+        "classic" => vec![
+            Color::from_rgb(255, 40, 40),
+            Color::from_rgb(40, 255, 80),
+            Color::from_rgb(60, 120, 255),
+            Color::from_rgb(255, 200, 40),
+            Color::from_rgb(190, 80, 255),
+            Color::from_rgb(255, 255, 255),
+        ],
+        "patriotic" => vec![
+            Color::from_rgb(230, 30, 50),
+            Color::from_rgb(255, 255, 255),
+            Color::from_rgb(40, 80, 230),
+        ],
+        "pastel" => vec![
+            Color::from_rgb(255, 179, 186),
+            Color::from_rgb(255, 223, 186),
+            Color::from_rgb(255, 255, 186),
+            Color::from_rgb(186, 255, 201),
+            Color::from_rgb(186, 225, 255),
+            Color::from_rgb(220, 190, 255),
+        ],
+        // Reds are listed more than once so they are picked more often.
+        "fire" => vec![
+            Color::from_rgb(255, 30, 0),
+            Color::from_rgb(255, 30, 0),
+            Color::from_rgb(255, 70, 0),
+            Color::from_rgb(255, 70, 0),
+            Color::from_rgb(255, 120, 0),
+            Color::from_rgb(255, 160, 20),
+            Color::from_rgb(255, 210, 40),
+        ],
+        "cool" => vec![
+            Color::from_rgb(80, 140, 255),
+            Color::from_rgb(60, 220, 255),
+            Color::from_rgb(140, 110, 255),
+            Color::from_rgb(200, 230, 255),
+        ],
+        "gold" => vec![
+            Color::from_rgb(255, 215, 80),
+            Color::from_rgb(255, 190, 40),
+            Color::from_rgb(255, 235, 160),
+            Color::from_rgb(240, 160, 60),
+        ],
+        "neon" => vec![
+            Color::from_rgb(255, 40, 200),
+            Color::from_rgb(40, 255, 230),
+            Color::from_rgb(180, 255, 40),
+            Color::from_rgb(160, 60, 255),
+        ],
+        _ => return None,
+    };
+    Some(colors)
+}
+
+/// Reads a palette file with one color per line, see [parse].
+pub fn load(path: &Path) -> Result<Vec<Color>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("read palette `{}`", path.display()))?;
+    parse(&text).with_context(|| format!("invalid palette `{}`", path.display()))
+}
+
+/// Parses one color per line, written as `#rrggbb` or `#rgb`.
+///
+/// Empty lines are skipped, as are comments: lines starting with a `#` that
+/// aren't a color.
+pub fn parse(text: &str) -> Result<Vec<Color>> {
+    let mut colors = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match Color::from_hex(line) {
+            Ok(color) => colors.push(color),
+            Err(_) if line.starts_with('#') => {}
+            Err(error) => return Err(error.context(format!("line {}", index + 1))),
+        }
+    }
+    if colors.is_empty() {
+        bail!("no colors");
+    }
+    Ok(colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_comments_and_empty_lines() {
+        let text = "# warm colors\n#comment\n\n  #ff0000\n#f80 \n#\n# #00f is out\n";
+        let colors = parse(text).unwrap();
+        assert_eq!(
+            colors,
+            vec![Color::from_rgb(255, 0, 0), Color::from_rgb(255, 136, 0)]
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = parse("#ff0000\n\nred\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 3");
+        assert!(format!("{error:#}").contains("invalid color `red`"));
+        assert_eq!(
+            parse("# only a comment\n").unwrap_err().to_string(),
+            "no colors"
+        );
+    }
+}
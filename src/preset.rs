@@ -0,0 +1,81 @@
+//! Fireworks launched on demand by the number keys 1 to 9.
+//!
+//! The built-in presets can be replaced from the config file, see
+//! [crate::config].
+
+use anyhow::{bail, Context, Result};
+use pixel_loop::color::Color;
+use serde::Deserialize;
+
+use crate::color::FromHex;
+use crate::sim::{FireworkKind, FIREWORK_KIND_NAMES};
+
+/// Most presets, one per number key.
+pub const MAX_PRESETS: usize = 9;
+
+/// A firework with a fixed look.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FireworkPreset {
+    pub kind: FireworkKind,
+    pub color: Color,
+    /// Factor applied to the size of the burst.
+    pub size: f64,
+    /// Factor applied to the number of burst sparks.
+    pub sparks: f64,
+}
+
+/// Presets of the number keys 1 to 9 unless the config file sets others.
+pub fn defaults() -> Vec<FireworkPreset> {
+    let preset = |kind, r, g, b, size, sparks| FireworkPreset {
+        kind,
+        color: Color::from_rgb(r, g, b),
+        size,
+        sparks,
+    };
+    vec![
+        preset(FireworkKind::Sphere, 255, 40, 40, 1.0, 1.0),
+        preset(FireworkKind::Willow, 40, 255, 80, 1.0, 1.0),
+        preset(FireworkKind::Palm, 255, 200, 40, 1.0, 1.0),
+        preset(FireworkKind::Ring, 60, 120, 255, 1.0, 1.0),
+        preset(FireworkKind::Sphere, 255, 255, 255, 1.5, 1.5),
+        preset(FireworkKind::Willow, 255, 215, 80, 1.3, 1.6),
+        preset(FireworkKind::Ring, 190, 80, 255, 1.3, 1.0),
+        preset(FireworkKind::Palm, 40, 255, 230, 1.2, 1.0),
+        preset(FireworkKind::Sphere, 255, 40, 200, 0.6, 0.5),
+    ]
+}
+
+/// A preset as written in the config file, with the size and number of
+/// sparks being optional.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PresetConfig {
+    kind: String,
+    color: String,
+    size: Option<f64>,
+    sparks: Option<f64>,
+}
+
+impl PresetConfig {
+    pub fn parse(&self) -> Result<FireworkPreset> {
+        let kind = FireworkKind::from_name(&self.kind).with_context(|| {
+            format!(
+                "unknown firework kind `{}`, expected one of: {}",
+                self.kind,
+                FIREWORK_KIND_NAMES.join(", ")
+            )
+        })?;
+        let factor = |value: Option<f64>, name: &str| match value {
+            Some(value) if !(0.1..=4.0).contains(&value) => {
+                bail!("{name} must be between 0.1 and 4, got {value}")
+            }
+            value => Ok(value.unwrap_or(1.0)),
+        };
+        Ok(FireworkPreset {
+            kind,
+            color: Color::from_hex(&self.color)?,
+            size: factor(self.size, "size")?,
+            sparks: factor(self.sparks, "sparks")?,
+        })
+    }
+}
@@ -0,0 +1,79 @@
+//! Recording of the rendered frames into an animated GIF.
+
+use anyhow::{Context, Result};
+use gif::{Encoder, Frame, Repeat};
+use pixel_loop::canvas::Canvas;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Shortest frame delay most GIF viewers respect.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+/// Quality of the color quantization, 1 is best and 30 is fastest.
+const QUANTIZE_SPEED: i32 = 10;
+
+pub struct GifRecorder {
+    encoder: Encoder<BufWriter<File>>,
+    width: u16,
+    height: u16,
+    frames_left: usize,
+    /// Time passed since the last captured frame.
+    pending: Duration,
+}
+
+impl GifRecorder {
+    /// Creates the GIF file at `path`, recording at most `max_frames` frames
+    /// of `width` x `height` pixels.
+    pub fn create(path: &Path, width: u16, height: u16, max_frames: usize) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("create recording `{}`", path.display()))?;
+        let mut encoder = Encoder::new(BufWriter::new(file), width, height, &[])?;
+        encoder.set_repeat(Repeat::Infinite)?;
+        Ok(Self {
+            encoder,
+            width,
+            height,
+            frames_left: max_frames,
+            pending: Duration::ZERO,
+        })
+    }
+
+    /// Adds the canvas contents as a frame, once enough time passed since the
+    /// previous one. Returns whether more frames should be recorded.
+    ///
+    /// A canvas of a different size than the recording is cropped or padded
+    /// with black.
+    pub fn capture<C: Canvas>(&mut self, canvas: &C, dt: Duration) -> Result<bool> {
+        self.pending += dt;
+        if self.pending < MIN_FRAME_DELAY {
+            return Ok(self.frames_left > 0);
+        }
+
+        let (width, height) = (self.width as u32, self.height as u32);
+        let mut rgb = vec![0u8; width as usize * height as usize * 3];
+        for y in 0..height.min(canvas.height()) {
+            for x in 0..width.min(canvas.width()) {
+                let color = canvas.get(x, y);
+                let i = (y * width + x) as usize * 3;
+                rgb[i..i + 3].copy_from_slice(&[color.r, color.g, color.b]);
+            }
+        }
+
+        let mut frame = Frame::from_rgb_speed(self.width, self.height, &rgb, QUANTIZE_SPEED);
+        // GIF delays are counted in hundredths of a second.
+        let centis = self.pending.as_millis() / 10;
+        frame.delay = centis.min(u16::MAX as u128) as u16;
+        self.pending -= Duration::from_millis(centis as u64 * 10);
+        self.encoder.write_frame(&frame)?;
+
+        self.frames_left = self.frames_left.saturating_sub(1);
+        Ok(self.frames_left > 0)
+    }
+
+    /// Writes the end of the GIF and flushes it to disk.
+    pub fn finish(self) -> Result<()> {
+        self.encoder.into_inner()?.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,438 @@
+//! Replays of whole shows, for reproducing exactly what was seen.
+//!
+//! A replay is a text file with one entry per line, starting with a header
+//! giving the seed and the terminal size in columns and rows:
+//!
+//! ```text
+//! seed 1791952324414428
+//! size 80 24
+//! ```
+//!
+//! Every following line is either an input event or a beat heard with
+//! `--audio-reactive`, prefixed by the update it happened in, or a launched
+//! firework, prefixed by the simulation step it was launched at:
+//!
+//! ```text
+//! 120 key space
+//! 130 click left 40 12
+//! 140 resize 100 30
+//! 150 input
+//! 160 interrupt
+//! 170 beat 0.85
+//! 42 launch sphere 37 48 0.12 -1.5 #ff3030 - 8751211855618904039
+//! 50 launch willow 12 48 0 -1.2 #30ff60 #ffd040 305417886 1.3 1.6
+//! ```
+//!
+//! Keys are one of `q`, `f`, `r`, `space`, `equal`, `minus`, `zero`,
+//! `enter`, `left`, `right`, `up`, `down`, `c`, `p` and the digits `1` to
+//! `9`, `input` stands for any other key pressed, which only counts as
+//! activity. A beat gives its volume from 0 to 1.
+//!
+//! A launch gives the burst shape, the column and row the rocket starts
+//! at, its horizontal and vertical speed, its color, the color of its inner
+//! sparks or `-` for a single tone and the seed of its random numbers,
+//! followed by the size and spark factor for presets. Empty lines and lines
+//! starting with `#` are ignored.
+//!
+//! Played back shows don't decide any launches, they only fire the recorded
+//! ones, and every firework draws its random numbers from its own seed. So
+//! a recorded show plays back the same however the rest of the show draws
+//! its random numbers.
+
+use anyhow::{bail, Context, Result};
+use crossterm::event::MouseButton;
+use pixel_loop::color::Color;
+use pixel_loop::input::KeyboardKey;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::color::FromHex;
+use crate::input::MouseClick;
+use crate::sim::{FireworkKind, FIREWORK_KIND_NAMES};
+
+/// Keys the show reacts to with their names in a replay.
+const KEYS: [(&str, KeyboardKey); 23] = [
+    ("q", KeyboardKey::Q),
+    ("f", KeyboardKey::F),
+    ("r", KeyboardKey::R),
+    ("space", KeyboardKey::Space),
+    ("equal", KeyboardKey::Equal),
+    ("minus", KeyboardKey::Minus),
+    ("zero", KeyboardKey::Zero),
+    ("enter", KeyboardKey::Enter),
+    ("left", KeyboardKey::Left),
+    ("right", KeyboardKey::Right),
+    ("up", KeyboardKey::Up),
+    ("down", KeyboardKey::Down),
+    ("c", KeyboardKey::C),
+    ("p", KeyboardKey::P),
+    ("1", KeyboardKey::One),
+    ("2", KeyboardKey::Two),
+    ("3", KeyboardKey::Three),
+    ("4", KeyboardKey::Four),
+    ("5", KeyboardKey::Five),
+    ("6", KeyboardKey::Six),
+    ("7", KeyboardKey::Seven),
+    ("8", KeyboardKey::Eight),
+    ("9", KeyboardKey::Nine),
+];
+
+/// Name of `key` in a replay, if the show reacts to it.
+pub fn key_name(key: KeyboardKey) -> Option<&'static str> {
+    KEYS.iter()
+        .find(|&&(_, known)| known == key)
+        .map(|&(name, _)| name)
+}
+
+fn key_from_name(name: &str) -> Option<KeyboardKey> {
+    KEYS.iter()
+        .find(|&&(known, _)| known == name)
+        .map(|&(_, key)| key)
+}
+
+/// Input during a single update of a recorded show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Key(KeyboardKey),
+    /// A key the show doesn't react to, which still counts as activity.
+    Input,
+    Click(MouseClick),
+    /// The terminal got the given number of columns and rows.
+    Resize(u16, u16),
+    /// Ctrl-C was pressed.
+    Interrupt,
+}
+
+/// A firework launched during a recorded show.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Launch {
+    /// Simulation step the firework was launched at.
+    pub frame: u64,
+    pub kind: FireworkKind,
+    pub x: i64,
+    pub y: i64,
+    pub speed: (f64, f64),
+    pub color: Color,
+    /// Color of the inner sparks of a two-tone firework.
+    pub inner_color: Option<Color>,
+    /// Size and spark factor of a preset, random ones without.
+    pub size: Option<(f64, f64)>,
+    /// Seed of the random numbers of the firework.
+    pub seed: u64,
+}
+
+/// Everything needed to play a show back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    /// Terminal size in columns and rows the show started with.
+    pub size: (u16, u16),
+    /// Input events with the update they happened in, in order.
+    pub events: Vec<(u64, Event)>,
+    /// Volumes of the beats heard with the update they were heard in, in
+    /// order.
+    pub beats: Vec<(u64, f64)>,
+    /// Launched fireworks in order.
+    pub launches: Vec<Launch>,
+}
+
+impl Replay {
+    /// An empty replay of a show started with `seed` in a terminal of `size`
+    /// columns and rows.
+    pub fn new(seed: u64, size: (u16, u16)) -> Self {
+        Self {
+            seed,
+            size,
+            events: vec![],
+            beats: vec![],
+            launches: vec![],
+        }
+    }
+
+    /// Reads the replay at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("read replay `{}`", path.display()))?;
+        Self::parse(&text).with_context(|| format!("invalid replay `{}`", path.display()))
+    }
+
+    /// Writes the replay to `path`.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_text())
+            .with_context(|| format!("write replay `{}`", path.display()))
+    }
+
+    /// Parses a replay written by [Replay::to_text].
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let mut header = |name: &str| -> Result<Vec<String>> {
+            let Some((number, line)) = lines.next() else {
+                bail!("missing `{name}` line");
+            };
+            let mut fields = line.split_whitespace();
+            if fields.next() != Some(name) {
+                bail!("line {number}: expected `{name}`");
+            }
+            Ok(fields.map(str::to_string).collect())
+        };
+        let seed = match &header("seed")?[..] {
+            [seed] => parse_field(seed, "seed")?,
+            _ => bail!("expected `seed <u64>`"),
+        };
+        let size = match &header("size")?[..] {
+            [columns, rows] => (parse_field(columns, "columns")?, parse_field(rows, "rows")?),
+            _ => bail!("expected `size <columns> <rows>`"),
+        };
+
+        let mut replay = Replay::new(seed, size);
+        for (number, line) in lines {
+            replay
+                .parse_line(line)
+                .with_context(|| format!("line {number}"))?;
+        }
+        Ok(replay)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<()> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [index, kind, ref rest @ ..] = fields[..] else {
+            bail!("expected an update or step followed by an entry");
+        };
+        let index = parse_field(index, "update")?;
+        let event = match (kind, rest) {
+            ("key", [name]) => Event::Key(key_from_name(name).with_context(|| {
+                let names: Vec<&str> = KEYS.iter().map(|&(name, _)| name).collect();
+                format!(
+                    "unknown key `{name}`, expected one of: {}",
+                    names.join(", ")
+                )
+            })?),
+            ("input", []) => Event::Input,
+            ("click", [button, column, row]) => Event::Click(MouseClick {
+                button: match *button {
+                    "left" => MouseButton::Left,
+                    "right" => MouseButton::Right,
+                    "middle" => MouseButton::Middle,
+                    _ => bail!("unknown mouse button `{button}`"),
+                },
+                column: parse_field(column, "column")?,
+                row: parse_field(row, "row")?,
+            }),
+            ("resize", [columns, rows]) => {
+                Event::Resize(parse_field(columns, "columns")?, parse_field(rows, "rows")?)
+            }
+            ("interrupt", []) => Event::Interrupt,
+            ("beat", [volume]) => {
+                self.beats.push((index, parse_field(volume, "volume")?));
+                return Ok(());
+            }
+            ("launch", [kind, x, y, speed_x, speed_y, color, inner_color, seed, size @ ..]) => {
+                let size = match *size {
+                    [] => None,
+                    [scale, sparks] => {
+                        Some((parse_field(scale, "size")?, parse_field(sparks, "sparks")?))
+                    }
+                    _ => bail!("expected a size and spark factor after the seed"),
+                };
+                self.launches.push(Launch {
+                    frame: index,
+                    kind: FireworkKind::from_name(kind).with_context(|| {
+                        format!(
+                            "unknown firework kind `{kind}`, expected one of: {}",
+                            FIREWORK_KIND_NAMES.join(", ")
+                        )
+                    })?,
+                    x: parse_field(x, "x")?,
+                    y: parse_field(y, "y")?,
+                    speed: (
+                        parse_field(speed_x, "speed")?,
+                        parse_field(speed_y, "speed")?,
+                    ),
+                    color: Color::from_hex(color)?,
+                    inner_color: match *inner_color {
+                        "-" => None,
+                        inner_color => Some(Color::from_hex(inner_color)?),
+                    },
+                    size,
+                    seed: parse_field(seed, "seed")?,
+                });
+                return Ok(());
+            }
+            _ => bail!("invalid `{kind}` entry"),
+        };
+        self.events.push((index, event));
+        Ok(())
+    }
+
+    /// The replay in the text format described in the [module
+    /// documentation](self).
+    pub fn to_text(&self) -> String {
+        let mut text = format!("seed {}\nsize {} {}\n", self.seed, self.size.0, self.size.1);
+        for (update, event) in &self.events {
+            let _ = match *event {
+                Event::Key(key) => {
+                    writeln!(text, "{update} key {}", key_name(key).unwrap_or("unknown"))
+                }
+                Event::Input => writeln!(text, "{update} input"),
+                Event::Click(click) => {
+                    let button = match click.button {
+                        MouseButton::Left => "left",
+                        MouseButton::Right => "right",
+                        MouseButton::Middle => "middle",
+                    };
+                    writeln!(
+                        text,
+                        "{update} click {button} {} {}",
+                        click.column, click.row
+                    )
+                }
+                Event::Resize(columns, rows) => writeln!(text, "{update} resize {columns} {rows}"),
+                Event::Interrupt => writeln!(text, "{update} interrupt"),
+            };
+        }
+        for (update, volume) in &self.beats {
+            let _ = writeln!(text, "{update} beat {volume}");
+        }
+        for launch in &self.launches {
+            let Color { r, g, b, .. } = launch.color;
+            let _ = write!(
+                text,
+                "{} launch {} {} {} {} {} #{r:02x}{g:02x}{b:02x}",
+                launch.frame,
+                launch.kind.name(),
+                launch.x,
+                launch.y,
+                launch.speed.0,
+                launch.speed.1,
+            );
+            let _ = match launch.inner_color {
+                Some(Color { r, g, b, .. }) => write!(text, " #{r:02x}{g:02x}{b:02x}"),
+                None => write!(text, " -"),
+            };
+            let _ = write!(text, " {}", launch.seed);
+            let _ = match launch.size {
+                Some((scale, sparks)) => writeln!(text, " {scale} {sparks}"),
+                None => writeln!(text),
+            };
+        }
+        text
+    }
+}
+
+/// A replay being played back.
+pub struct Playback {
+    replay: Replay,
+    next_event: usize,
+    next_beat: usize,
+    next_launch: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next_event: 0,
+            next_beat: 0,
+            next_launch: 0,
+        }
+    }
+
+    /// Terminal size in columns and rows the show started with.
+    pub fn size(&self) -> (u16, u16) {
+        self.replay.size
+    }
+
+    /// Input events of the `update`, skipping those of earlier updates.
+    pub fn events(&mut self, update: u64) -> Vec<Event> {
+        let mut events = vec![];
+        while let Some(&(index, event)) = self.replay.events.get(self.next_event) {
+            if index > update {
+                break;
+            }
+            self.next_event += 1;
+            if index == update {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Volumes of the beats heard in the `update`, skipping those of
+    /// earlier updates.
+    pub fn beats(&mut self, update: u64) -> Vec<f64> {
+        let mut volumes = vec![];
+        while let Some(&(index, volume)) = self.replay.beats.get(self.next_beat) {
+            if index > update {
+                break;
+            }
+            self.next_beat += 1;
+            if index == update {
+                volumes.push(volume);
+            }
+        }
+        volumes
+    }
+
+    /// The next launch due by simulation step `frame`, if any.
+    pub fn next_launch(&mut self, frame: u64) -> Option<Launch> {
+        let launch = self.replay.launches.get(self.next_launch).copied()?;
+        if launch.frame > frame {
+            return None;
+        }
+        self.next_launch += 1;
+        Some(launch)
+    }
+}
+
+fn parse_field<T>(value: &str, name: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .parse()
+        .with_context(|| format!("invalid {name} `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        let mut replay = Replay::new(42, (80, 24));
+        replay.events = vec![(3, Event::Key(KeyboardKey::Space)), (5, Event::Input)];
+        replay.beats = vec![(7, 0.85)];
+        replay.launches = vec![
+            Launch {
+                frame: 12,
+                kind: FireworkKind::Willow,
+                x: 37,
+                y: 48,
+                speed: (0.1, -1.3),
+                color: Color::from_rgb(255, 48, 48),
+                inner_color: Some(Color::from_rgb(255, 208, 64)),
+                size: Some((1.3, 1.6)),
+                seed: 305417886,
+            },
+            Launch {
+                frame: 20,
+                kind: FireworkKind::Sphere,
+                x: 12,
+                y: 48,
+                speed: (0.0, -1.2),
+                color: Color::from_rgb(48, 255, 96),
+                inner_color: None,
+                size: None,
+                seed: 8751211855618904039,
+            },
+        ];
+        assert_eq!(Replay::parse(&replay.to_text()).unwrap(), replay);
+    }
+}
@@ -0,0 +1,44 @@
+//! Single frames saved as PNG images.
+
+use anyhow::{Context, Result};
+use pixel_loop::canvas::Canvas;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of a new screenshot in the working directory, unique to the
+/// millisecond it was taken at.
+pub fn timestamped_path() -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    PathBuf::from(format!("fireworks-{millis}.png"))
+}
+
+/// Writes the canvas contents to a PNG image at `path`, one image pixel per
+/// canvas pixel.
+pub fn save<C: Canvas>(canvas: &C, path: &Path) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("create screenshot `{}`", path.display()))?;
+    let (width, height) = (canvas.width(), canvas.height());
+    let mut rgb = Vec::with_capacity(width as usize * height as usize * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let color = canvas.get(x, y);
+            rgb.extend([color.r, color.g, color.b]);
+        }
+    }
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .with_context(|| format!("write screenshot `{}`", path.display()))?;
+    writer
+        .write_image_data(&rgb)
+        .with_context(|| format!("write screenshot `{}`", path.display()))?;
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Scripted shows launching fireworks at fixed times.
+//!
+//! A script is a text file with one launch per line written as
+//! `frame x y color`: the simulation step to launch at, the column to launch
+//! from, the pixel row to burst at and a `#rrggbb` or `#rgb` color. Empty
+//! lines and lines starting with `#` are ignored.
+
+use anyhow::{bail, Context, Result};
+use pixel_loop::color::Color;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::color::FromHex;
+
+/// A firework launched by a script.
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduledLaunch {
+    /// Simulation step at which the firework is launched.
+    pub frame: u64,
+    /// Canvas column the rocket rises in.
+    pub x: u32,
+    /// Canvas row at which the rocket bursts.
+    pub y: u32,
+    pub color: Color,
+}
+
+/// Reads the script at `path`, sorted by launch frame.
+pub fn load(path: &Path) -> Result<Vec<ScheduledLaunch>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("read script `{}`", path.display()))?;
+    parse(&text).with_context(|| format!("invalid script `{}`", path.display()))
+}
+
+/// Parses the launches of a script, sorted by launch frame.
+pub fn parse(text: &str) -> Result<Vec<ScheduledLaunch>> {
+    let mut launches = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let launch = parse_line(line).with_context(|| format!("line {}", index + 1))?;
+        launches.push(launch);
+    }
+    // Stable, so launches of the same frame keep their order.
+    launches.sort_by_key(|launch| launch.frame);
+    Ok(launches)
+}
+
+fn parse_line(line: &str) -> Result<ScheduledLaunch> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [frame, x, y, color] = fields[..] else {
+        bail!("expected `frame x y color`, found {} fields", fields.len());
+    };
+    Ok(ScheduledLaunch {
+        frame: parse_field(frame, "frame")?,
+        x: parse_field(x, "x")?,
+        y: parse_field(y, "y")?,
+        color: Color::from_hex(color)?,
+    })
+}
+
+fn parse_field<T>(value: &str, name: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .parse()
+        .with_context(|| format!("invalid {name} `{value}`"))
+}
@@ -0,0 +1,149 @@
+//! Shapes bursts can take instead of spraying sparks randomly.
+//!
+//! Text is drawn with a tiny 3 x 5 pixel font of capital letters, digits and
+//! a few punctuation marks.
+
+use anyhow::{bail, Result};
+
+/// Most characters of a text shape, so it still fits on small terminals.
+pub const MAX_TEXT_LEN: usize = 8;
+
+/// Width and height of a glyph of the font in pixels.
+const GLYPH_SIZE: (usize, usize) = (3, 5);
+/// Pixels between two glyphs of a text shape.
+const GLYPH_SPACING: usize = 1;
+
+/// Outline of a heart, `#` marking a pixel.
+const HEART: [&str; 8] = [
+    ".##...##.",
+    "#..#.#..#",
+    "#...#...#",
+    "#.......#",
+    ".#.....#.",
+    "..#...#..",
+    "...#.#...",
+    "....#....",
+];
+
+/// What a shaped burst looks like.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shape {
+    Heart,
+    /// A short word in capital letters.
+    Text(String),
+}
+
+impl Shape {
+    /// Parses `heart` or a text of at most [MAX_TEXT_LEN] characters the
+    /// font knows.
+    pub fn parse(value: &str) -> Result<Self> {
+        if value == "heart" {
+            return Ok(Shape::Heart);
+        }
+        let text = value.to_uppercase();
+        if text.trim().is_empty() {
+            bail!("expected `heart` or some text");
+        }
+        if text.chars().count() > MAX_TEXT_LEN {
+            bail!("text has more than {MAX_TEXT_LEN} characters");
+        }
+        if let Some(unknown) = text.chars().find(|&c| glyph(c).is_none()) {
+            bail!("`{unknown}` can't be drawn, only letters, digits, spaces and !?.-+ can");
+        }
+        Ok(Shape::Text(text))
+    }
+
+    /// Pixels of the shape as offsets from its center, with y growing
+    /// downwards.
+    pub fn points(&self) -> Vec<(f64, f64)> {
+        let rows: Vec<Vec<bool>> = match self {
+            Shape::Heart => HEART
+                .iter()
+                .map(|row| row.chars().map(|c| c == '#').collect())
+                .collect(),
+            Shape::Text(text) => text_rows(text),
+        };
+        let height = rows.len() as f64;
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0) as f64;
+        let mut points = vec![];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &set) in row.iter().enumerate() {
+                if set {
+                    points.push((
+                        x as f64 - (width - 1.0) / 2.0,
+                        y as f64 - (height - 1.0) / 2.0,
+                    ));
+                }
+            }
+        }
+        points
+    }
+}
+
+/// Rows of pixels of `text` set in the font.
+fn text_rows(text: &str) -> Vec<Vec<bool>> {
+    let (width, height) = GLYPH_SIZE;
+    let mut rows = vec![vec![]; height];
+    for (index, c) in text.chars().enumerate() {
+        let Some(glyph) = glyph(c) else {
+            continue;
+        };
+        for (row, bits) in rows.iter_mut().zip(glyph) {
+            if index > 0 {
+                row.extend([false; GLYPH_SPACING]);
+            }
+            // The leftmost pixel is the highest of the three bits.
+            row.extend((0..width).rev().map(|bit| bits >> bit & 1 == 1));
+        }
+    }
+    rows
+}
+
+/// Rows of the glyph of `c` from top to bottom, each a 3 bit pattern.
+fn glyph(c: char) -> Option<[u8; 5]> {
+    Some(match c {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '?' => [0b110, 0b001, 0b010, 0b000, 0b010],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        _ => return None,
+    })
+}
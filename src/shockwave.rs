@@ -0,0 +1,61 @@
+//! Thin rings expanding from bursts like a shockwave.
+
+use pixel_loop::canvas::Canvas;
+use pixel_loop::color::Color;
+
+use crate::canvas::{self, Sky};
+use crate::color::Blend;
+use crate::sim::Burst;
+
+/// Pixels the ring grows by per frame.
+const SPEED: f64 = 1.5;
+/// Largest radius in pixels of the ring of a burst without sparks.
+const BASE_RADIUS: f64 = 6.0;
+/// Pixels added to the largest radius per spark of the burst.
+const RADIUS_PER_SPARK: f64 = 0.2;
+/// Largest radius in pixels of any ring.
+const MAX_RADIUS: f64 = 20.0;
+
+pub struct Shockwave {
+    center: (f64, f64),
+    radius: f64,
+    max_radius: f64,
+    color: Color,
+}
+
+impl Shockwave {
+    /// A ring around `burst`, growing larger for bursts with more sparks.
+    pub fn new(burst: &Burst) -> Self {
+        Self {
+            center: burst.position,
+            radius: 1.0,
+            max_radius: (BASE_RADIUS + burst.sparks as f64 * RADIUS_PER_SPARK).min(MAX_RADIUS),
+            color: burst.color,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.radius += SPEED;
+    }
+
+    /// Brightness of the ring, fading from 1 to 0 as it grows.
+    fn brightness(&self) -> f64 {
+        1.0 - self.radius / self.max_radius
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.radius >= self.max_radius || self.brightness() <= 0.0
+    }
+
+    /// Draws the outline of the ring, fading into the `sky` around its
+    /// center.
+    pub fn draw<C: Canvas>(&self, canvas: &mut C, sky: &Sky) {
+        if self.is_dead() {
+            return;
+        }
+        let center = (self.center.0.round() as i64, self.center.1.round() as i64);
+        let background = sky.at(center.1, canvas.height());
+        let color = Color::blend(&background, &self.color, self.brightness());
+        canvas::draw_circle_outline(canvas, center, self.radius.round() as i64, &color);
+    }
+}
@@ -0,0 +1,2279 @@
+//! Firework physics.
+//!
+//! Independent of the terminal: particles only know their colors and draw
+//! onto any pixel_loop [Canvas].
+
+use crate::canvas;
+use crate::color::Blend;
+use anyhow::Result;
+use pixel_loop::canvas::{Canvas, RenderableCanvas};
+use pixel_loop::color::{Color, HslColor};
+use pixel_loop::rand::distributions::{Distribution, Standard};
+use pixel_loop::rand::{Rng, SeedableRng};
+use pixel_loop::rand_xoshiro::Xoshiro256PlusPlus;
+use std::collections::VecDeque;
+use std::f64::consts::{PI, TAU};
+use std::ops::Range;
+use std::rc::Rc;
+
+/// Factors the number of burst sparks is varied by, picked per firework.
+const BURST_SIZES: Range<f64> = 0.6..1.6;
+/// Sparks of a sphere burst on an empty canvas. Other kinds have their
+/// counts scaled the same way as the sphere.
+const BASE_SPARKS: f64 = 15.0;
+/// Most sparks of a sphere burst however large the canvas is, before the
+/// per firework variation.
+const MAX_SPHERE_SPARKS: f64 = 100.0;
+/// Lifetime below which crackling sparks start to flicker.
+const CRACKLE_LIFETIME: f64 = 0.2;
+/// Chance per frame of a dying crackling spark to flash.
+const CRACKLE_CHANCE: f64 = 0.15;
+/// Brightness of a flashing spark, 1 being its full color.
+const CRACKLE_BRIGHTNESS: f64 = 1.0;
+/// Chance per frame of a live spark to leave a twinkle behind.
+const TWINKLE_CHANCE: f64 = 0.05;
+/// Lifetime a twinkle loses per frame, so it only lasts a few frames.
+const TWINKLE_FADING: f64 = 0.15;
+/// Largest distance in pixels burst sparks start away from the burst origin,
+/// before scaling.
+const SPARK_JITTER: f64 = 1.5;
+/// Sizes burst sparks get with varied sizes, in pixels added to the spark
+/// size, with how often each is picked and the factor applied to how long
+/// the spark lasts.
+const VARIED_SIZES: [((u32, u32), u32, f64); 3] =
+    [((0, 0), 8, 1.0), ((0, 1), 1, 1.2), ((1, 0), 1, 1.2)];
+/// Saturation in percent the fastest sparks lose at full depth.
+const DEPTH_DESATURATION: f64 = 30.0;
+/// Lightness in percent the fastest sparks lose at full depth.
+const DEPTH_DIMMING: f64 = 10.0;
+/// Fraction of the speed of the fastest spark below which sparks of a two
+/// tone burst get the inner color.
+const INNER_SPEED: f64 = 0.5;
+/// Distance in pixels between the sparks of a shaped burst, before scaling.
+const SHAPE_SCALE: f64 = 3.0;
+/// Fraction of its final distance from the burst origin a spark of a shaped
+/// burst flies per frame at first. With the default drag it ends up about
+/// at that distance.
+const SHAPE_SPEED: f64 = 0.025;
+/// Lifetime a spark of a shaped burst loses per frame.
+const SHAPE_FADING: f64 = 0.012;
+/// Sparks of the white flash at the center of a burst, before scaling.
+const CORE_SPARKS: usize = 8;
+/// Color of the flash powder at the center of a burst.
+const CORE_COLOR: Color = Color::from_rgb(255, 255, 255);
+/// Lightness in percent of a rocket tinted with the color of its burst.
+const ROCKET_TINT_LIGHTNESS: f64 = 85.0;
+/// Color of the flash of a firing mortar.
+const MORTAR_COLOR: Color = Color::from_rgb(255, 230, 160);
+/// Frames per pulse of the flash of a firing mortar.
+const MORTAR_PULSE_FRAMES: f64 = 6.0;
+/// Color of the sparks a rising rocket leaves behind.
+const LAUNCH_TRAIL_COLOR: Color = Color::from_rgb(255, 200, 120);
+/// Lifetime a launch trail spark loses per frame.
+const LAUNCH_TRAIL_FADING: f64 = 0.08;
+/// Lifetime a fizzling rocket loses per frame while rising.
+const FIZZLE_FADING: f64 = 0.02;
+/// Sparks of the burst of a weak dud.
+const WEAK_DUD_SPARKS: usize = 3;
+/// Deepest stage of a multi-stage firework, counting the first burst as 0.
+const MAX_STAGE_DEPTH: u32 = 2;
+/// Steps after which a firework counts as dead however many of its
+/// particles are still alive, unless set with [Firework::with_max_age].
+/// Sparks which never fade or leave the canvas can't keep it around forever.
+const MAX_AGE: u32 = 600;
+/// Number of sparks of a multi-stage burst which burst again.
+const SECONDARY_ROCKETS: usize = 4;
+
+/// Distance in pixels below which attractors pull no harder, so sparks
+/// passing right through one aren't flung away.
+const MIN_ATTRACTOR_DISTANCE: f64 = 3.0;
+
+/// An invisible point pulling sparks towards it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attractor {
+    pub position: (f64, f64),
+    /// Acceleration towards the attractor at a distance of one pixel, falling
+    /// off with the square of the distance.
+    pub strength: f64,
+}
+
+/// Forces acting on every particle.
+#[derive(Clone, Copy)]
+pub struct Forces<'a> {
+    /// Downward acceleration per frame.
+    pub gravity: f64,
+    /// Horizontal acceleration per frame, positive values blow to the right.
+    pub wind: f64,
+    /// Fraction of its speed a particle loses per frame to air resistance.
+    pub drag: f64,
+    pub attractors: &'a [Attractor],
+}
+
+/// A rocket bursting, as reported by [Firework::update].
+#[derive(Debug, Clone, Copy)]
+pub struct Burst {
+    pub position: (f64, f64),
+    pub color: Color,
+    /// Number of sparks the burst created.
+    pub sparks: usize,
+}
+
+/// What happens to particles crossing the left or right edge of the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    /// They come back in on the opposite edge.
+    Wrap,
+    /// They stop at the edge and slide along it.
+    Clamp,
+    /// They die.
+    Cull,
+}
+
+/// Names of all boundaries, as accepted by [Boundary::from_name].
+pub const BOUNDARY_NAMES: &[&str] = &["wrap", "clamp", "cull"];
+
+impl Boundary {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "wrap" => Some(Boundary::Wrap),
+            "clamp" => Some(Boundary::Clamp),
+            "cull" => Some(Boundary::Cull),
+            _ => None,
+        }
+    }
+}
+
+/// Edges of the canvas particles run into.
+#[derive(Debug, Clone, Copy)]
+pub struct Edges {
+    /// Width of the canvas and what happens to particles crossing its left
+    /// or right edge, if anything.
+    pub sides: Option<(f64, Boundary)>,
+    /// The ground particles bounce off and settle on, if any.
+    pub ground: Option<Ground>,
+}
+
+/// Bottom edge of the canvas, as far as particles are concerned.
+#[derive(Debug, Clone, Copy)]
+pub struct Ground {
+    /// y coordinate of the ground.
+    pub y: f64,
+    /// Fraction of its downward speed a particle keeps when first bouncing
+    /// off the ground.
+    pub bounce: f64,
+    /// Fraction of its sideways speed a particle keeps per frame on the
+    /// ground.
+    pub friction: f64,
+}
+
+impl Edges {
+    /// No edges at all, particles fly on forever.
+    pub const NONE: Edges = Edges {
+        sides: None,
+        ground: None,
+    };
+}
+
+/// Size of the canvas the simulation runs on and the physics within it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimParams {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels, two per terminal row.
+    pub height: u32,
+    /// Downward acceleration of rockets and sparks per step.
+    pub gravity: f64,
+    /// Fraction of their speed sparks lose per step.
+    pub drag: f64,
+    /// Horizontal acceleration of sparks per step, positive values blow to
+    /// the right.
+    pub wind: f64,
+    /// What happens to sparks leaving to the side.
+    pub boundary: Boundary,
+    /// Whether sparks bounce off and settle on the bottom edge.
+    pub ground: bool,
+    /// Fraction of their downward speed sparks keep when first bouncing off
+    /// the ground.
+    pub ground_bounce: f64,
+    /// Fraction of their sideways speed sparks keep per step on the ground.
+    pub ground_friction: f64,
+    /// Chance per step to launch a random firework.
+    pub spawn_chance: f64,
+    /// Sparks of a sphere burst at the default scale, on the default canvas.
+    pub sphere_sparks: usize,
+    /// Largest speed across of sphere sparks at the default scale.
+    pub sphere_speed: f64,
+    /// Lifetime a burst spark loses per step, unless its kind fades it
+    /// differently.
+    pub fading: f64,
+}
+
+impl SimParams {
+    /// The defaults of the command line, on the canvas of an 80 x 24
+    /// terminal.
+    pub const DEFAULT: SimParams = SimParams {
+        width: 80,
+        height: 48,
+        gravity: 0.02,
+        drag: 0.02,
+        wind: 0.0,
+        boundary: Boundary::Cull,
+        ground: false,
+        ground_bounce: 0.3,
+        ground_friction: 0.8,
+        spawn_chance: 0.10,
+        sphere_sparks: 25,
+        sphere_speed: 1.5,
+        fading: 0.01,
+    };
+
+    /// Forces acting on every spark, including the pull of `attractors`.
+    pub fn forces<'a>(&self, attractors: &'a [Attractor]) -> Forces<'a> {
+        Forces {
+            gravity: self.gravity,
+            wind: self.wind,
+            drag: self.drag,
+            attractors,
+        }
+    }
+
+    /// Edges of the canvas sparks run into.
+    pub fn edges(&self) -> Edges {
+        Edges {
+            sides: Some((self.width as f64, self.boundary)),
+            ground: self.ground.then_some(Ground {
+                y: self.height as f64,
+                bounce: self.ground_bounce,
+                friction: self.ground_friction,
+            }),
+        }
+    }
+}
+
+impl Default for SimParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// How the brightness of a particle follows its remaining lifetime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FadeCurve {
+    /// Fades at a constant rate.
+    Linear,
+    /// Stays bright for long and drops off quickly at the end.
+    EaseOut,
+    /// Dims quickly at first and lingers faintly at the end.
+    Quadratic,
+    /// Stays at full brightness until the particle vanishes.
+    Solid,
+}
+
+/// Names of all fade curves, as accepted by [FadeCurve::from_name].
+pub const FADE_CURVE_NAMES: &[&str] = &["linear", "easeout", "quadratic", "solid"];
+
+impl FadeCurve {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(FadeCurve::Linear),
+            "easeout" => Some(FadeCurve::EaseOut),
+            "quadratic" => Some(FadeCurve::Quadratic),
+            "solid" => Some(FadeCurve::Solid),
+            _ => None,
+        }
+    }
+}
+
+/// Brightness from 0 to 1 of a particle with `lifetime` left, from 1 when
+/// new to 0 when dead.
+pub fn brightness(lifetime: f64, curve: FadeCurve) -> f64 {
+    let lifetime = lifetime.clamp(0.0, 1.0);
+    match curve {
+        FadeCurve::Linear => lifetime,
+        FadeCurve::EaseOut => 1.0 - (1.0 - lifetime).powi(2),
+        FadeCurve::Quadratic => lifetime * lifetime,
+        // Dead particles aren't drawn at all.
+        FadeCurve::Solid => 1.0,
+    }
+}
+
+/// How particles are put onto the canvas.
+#[derive(Clone, Copy)]
+pub struct DrawStyle {
+    /// Colors of the sky, which particles fade into.
+    pub sky: canvas::Sky,
+    /// Whether particles add their light to the pixels below instead of
+    /// covering them.
+    pub additive: bool,
+    pub fade_curve: FadeCurve,
+    /// Speed in pixels per frame above which particles are drawn as a streak
+    /// from their previous position, never without one.
+    pub motion_blur: Option<f64>,
+    /// Whether particles are antialiased, spreading over the pixels around
+    /// their exact position instead of snapping to the nearest one.
+    pub smooth: bool,
+}
+
+pub struct Particle {
+    position: (f64, f64),
+    dimensions: (u32, u32),
+    lifetime: f64,
+    fading: f64,
+    speed: (f64, f64),
+    /// Acceleration of this particle on top of the global forces.
+    acceleration: (f64, f64),
+    color: Color,
+    /// Previous positions, most recent first. Never holds more than
+    /// `trail_length` entries.
+    trail: VecDeque<(f64, f64)>,
+    trail_length: usize,
+    /// Whether the particle already bounced off the ground once.
+    bounced: bool,
+    /// Whether the particle flashes brightly in the current frame.
+    flashing: bool,
+    /// Whether the particle is exhaust of a rising rocket rather than part
+    /// of a burst.
+    exhaust: bool,
+    /// Degrees the hue turns by over the particle's lifetime.
+    hue_shift: f64,
+    /// Fewest frames the particle lasts however fast it fades, 0 for no
+    /// limit.
+    min_frames: u32,
+    /// Factors applied to the global gravity and drag for this particle.
+    force_scale: (f64, f64),
+}
+
+impl Particle {
+    pub fn new(x: i64, y: i64, width: u32, height: u32, color: Color) -> Self {
+        Self {
+            position: (x as f64, y as f64),
+            dimensions: (width, height),
+            lifetime: 1.0,
+            fading: SimParams::DEFAULT.fading,
+            speed: (0.0, 0.0),
+            acceleration: (0.0, 0.0),
+            color,
+            trail: VecDeque::new(),
+            trail_length: 0,
+            bounced: false,
+            flashing: false,
+            exhaust: false,
+            hue_shift: 0.0,
+            min_frames: 0,
+            force_scale: (1.0, 1.0),
+        }
+    }
+
+    pub fn with_fading(self, fading: f64) -> Self {
+        Self { fading, ..self }
+    }
+
+    pub fn with_speed(self, x: f64, y: f64) -> Self {
+        Self {
+            speed: (x, y),
+            ..self
+        }
+    }
+
+    pub fn with_acceleration(self, x: f64, y: f64) -> Self {
+        Self {
+            acceleration: (x, y),
+            ..self
+        }
+    }
+
+    pub fn with_dimensions(self, width: u32, height: u32) -> Self {
+        Self {
+            dimensions: (width, height),
+            ..self
+        }
+    }
+
+    /// Keeps the particle alive for at least `frames` frames, unless it
+    /// leaves the canvas, by fading it slower if needed.
+    pub fn with_min_frames(self, frames: u32) -> Self {
+        Self {
+            min_frames: frames,
+            ..self
+        }
+    }
+
+    /// Scales the global gravity and drag acting on the particle by
+    /// `gravity` and `drag`.
+    pub fn with_force_scale(self, gravity: f64, drag: f64) -> Self {
+        Self {
+            force_scale: (gravity, drag),
+            ..self
+        }
+    }
+
+    /// Turns the hue by `degrees` while the particle fades out, like a
+    /// cooling ember.
+    pub fn with_hue_shift(self, degrees: f64) -> Self {
+        Self {
+            hue_shift: degrees,
+            ..self
+        }
+    }
+
+    /// Remembers the last `length` positions, which are drawn as a fading
+    /// streak behind the particle.
+    pub fn with_trail(mut self, length: usize) -> Self {
+        self.trail.reserve(length);
+        Self {
+            trail_length: length,
+            ..self
+        }
+    }
+
+    /// Draws the particle, fading it out as its lifetime runs out.
+    pub fn draw<C: Canvas>(&self, canvas: &mut C, style: &DrawStyle) {
+        self.draw_side(canvas, style, false);
+    }
+
+    /// Draws the particle, reflected across the vertical center line of the
+    /// canvas if `mirrored`.
+    fn draw_side<C: Canvas>(&self, canvas: &mut C, style: &DrawStyle, mirrored: bool) {
+        if self.lifetime <= 0.0 {
+            return;
+        }
+        let canvas_width = canvas.width() as f64;
+        let place = |(x, y): (f64, f64)| {
+            if mirrored {
+                (canvas_width - x - self.dimensions.0 as f64, y)
+            } else {
+                (x, y)
+            }
+        };
+
+        let color = self.current_color();
+        let faded = brightness(self.lifetime, style.fade_curve);
+        // Oldest positions first, so the brighter ones are drawn on top.
+        let steps = self.trail.len() as f64 + 1.0;
+        for (i, position) in self.trail.iter().enumerate().rev() {
+            let brightness = faded * (1.0 - (i as f64 + 1.0) / steps);
+            self.draw_at(canvas, style, &color, place(*position), brightness);
+        }
+
+        let brightness = if self.flashing {
+            CRACKLE_BRIGHTNESS
+        } else {
+            faded
+        };
+        let (x, y) = place(self.position);
+        let speed = self.speed.0.hypot(self.speed.1);
+        if style.motion_blur.is_some_and(|threshold| speed > threshold) {
+            // Where the particle was a frame ago, reflected with it.
+            let (from_x, from_y) = place((
+                self.position.0 - self.speed.0,
+                self.position.1 - self.speed.1,
+            ));
+            let streak = canvas::line_pixels(
+                (from_x.round() as i64, from_y.round() as i64),
+                (x.round() as i64, y.round() as i64),
+            );
+            // Fading in towards the current position, which is drawn last.
+            let steps = streak.len() as f64;
+            for (i, &(px, py)) in streak.iter().enumerate() {
+                let share = (i as f64 + 1.0) / steps;
+                self.draw_at(
+                    canvas,
+                    style,
+                    &color,
+                    (px as f64, py as f64),
+                    brightness * share,
+                );
+            }
+            return;
+        }
+        self.draw_at(canvas, style, &color, (x, y), brightness);
+    }
+
+    /// Color with the hue turned by how far the lifetime ran out.
+    fn current_color(&self) -> Color {
+        if self.hue_shift == 0.0 {
+            return self.color;
+        }
+        let hsl = self.color.as_hsl();
+        let hue = (hsl.h + (1.0 - self.lifetime) * self.hue_shift).rem_euclid(360.0);
+        HslColor::new(hue, hsl.s, hsl.l).into()
+    }
+
+    fn draw_at<C: Canvas>(
+        &self,
+        canvas: &mut C,
+        style: &DrawStyle,
+        color: &Color,
+        position: (f64, f64),
+        brightness: f64,
+    ) {
+        if !style.smooth {
+            let (x, y) = (position.0.round() as i64, position.1.round() as i64);
+            self.fill_at(canvas, style, color, (x, y), brightness, 1.0);
+            return;
+        }
+        let (x, y) = (position.0.floor(), position.1.floor());
+        let (fraction_x, fraction_y) = (position.0 - x, position.1 - y);
+        for (dx, share_x) in [(0, 1.0 - fraction_x), (1, fraction_x)] {
+            for (dy, share_y) in [(0, 1.0 - fraction_y), (1, fraction_y)] {
+                let coverage = share_x * share_y;
+                if coverage > 0.0 {
+                    let pixel = (x as i64 + dx, y as i64 + dy);
+                    self.fill_at(canvas, style, color, pixel, brightness, coverage);
+                }
+            }
+        }
+    }
+
+    /// Draws the particle at the pixel `position`, covering only `coverage`
+    /// of each of its pixels.
+    fn fill_at<C: Canvas>(
+        &self,
+        canvas: &mut C,
+        style: &DrawStyle,
+        color: &Color,
+        position: (i64, i64),
+        brightness: f64,
+        coverage: f64,
+    ) {
+        let (x, y) = position;
+        let (width, height) = self.dimensions;
+        // Only the part on the canvas is drawn, particles can be partly or
+        // completely beyond any of its edges.
+        let (left, top) = (x.max(0), y.max(0));
+        let right = (x + width as i64).min(canvas.width() as i64);
+        let bottom = (y + height as i64).min(canvas.height() as i64);
+        if left >= right || top >= bottom {
+            return;
+        }
+        if !style.additive {
+            let background = style.sky.at(top, canvas.height());
+            let color = Color::blend(&background, color, brightness);
+            if coverage < 1.0 {
+                // Partly covered pixels keep some of what was below.
+                for py in top as u32..bottom as u32 {
+                    for px in left as u32..right as u32 {
+                        let mixed = Color::blend(canvas.get(px, py), &color, coverage);
+                        canvas.set(px, py, &mixed);
+                    }
+                }
+                return;
+            }
+            let (width, height) = ((right - left) as u32, (bottom - top) as u32);
+            canvas::fill_rect(canvas, left, top, width, height, &color);
+            return;
+        }
+
+        let light = Color::blend(&Color::from_rgb(0, 0, 0), color, brightness * coverage);
+        for py in top as u32..bottom as u32 {
+            for px in left as u32..right as u32 {
+                let color = Color::add(canvas.get(px, py), &light);
+                canvas.set(px, py, &color);
+            }
+        }
+    }
+
+    /// Advances the particle by one frame under the global `forces`.
+    ///
+    /// With a ground the particle bounces off it once and then slides to a
+    /// halt on it, while still fading out as usual. The sides of the `edges`
+    /// wrap, stop or kill the particle.
+    pub fn update(&mut self, forces: &Forces, edges: &Edges) {
+        if self.lifetime <= 0.0 {
+            return;
+        }
+        let (gravity_scale, drag_scale) = self.force_scale;
+        let damping = 1.0 - forces.drag * drag_scale;
+        let pull = self.attraction(forces.attractors);
+        self.speed = (
+            (self.speed.0 + self.acceleration.0 + forces.wind + pull.0) * damping,
+            (self.speed.1 + self.acceleration.1 + forces.gravity * gravity_scale + pull.1)
+                * damping,
+        );
+        self.lifetime -= if self.min_frames > 0 {
+            self.fading.min(1.0 / self.min_frames as f64)
+        } else {
+            self.fading
+        };
+        if self.trail_length > 0 {
+            if self.trail.len() == self.trail_length {
+                self.trail.pop_back();
+            }
+            self.trail.push_front(self.position);
+        }
+        self.position = (
+            self.position.0 + self.speed.0,
+            self.position.1 + self.speed.1,
+        );
+
+        if let Some((width, boundary)) = edges.sides {
+            self.cross_sides(width, boundary);
+        }
+
+        if let Some(ground) = edges.ground {
+            let bottom = ground.y - self.dimensions.1 as f64;
+            if self.position.1 > bottom {
+                self.position.1 = bottom;
+                if self.speed.1 > 0.0 {
+                    self.speed.1 = if self.bounced {
+                        0.0
+                    } else {
+                        -self.speed.1 * ground.bounce
+                    };
+                    self.bounced = true;
+                }
+                self.speed.0 *= ground.friction;
+            }
+        }
+    }
+
+    /// Applies the `boundary` of a canvas `width` pixels wide to a particle
+    /// beyond its left or right edge.
+    fn cross_sides(&mut self, width: f64, boundary: Boundary) {
+        let right = width - self.dimensions.0 as f64;
+        match boundary {
+            Boundary::Wrap => self.position.0 = self.position.0.rem_euclid(width),
+            Boundary::Clamp => {
+                if self.position.0 < 0.0 || self.position.0 > right {
+                    self.position.0 = self.position.0.clamp(0.0, right.max(0.0));
+                    self.speed.0 = 0.0;
+                }
+            }
+            Boundary::Cull => {
+                if self.position.0 + self.dimensions.0 as f64 <= 0.0 || self.position.0 >= width {
+                    self.lifetime = 0.0;
+                }
+            }
+        }
+    }
+
+    /// Acceleration towards all `attractors`.
+    fn attraction(&self, attractors: &[Attractor]) -> (f64, f64) {
+        let mut pull = (0.0, 0.0);
+        for attractor in attractors {
+            let dx = attractor.position.0 - self.position.0;
+            let dy = attractor.position.1 - self.position.1;
+            let distance = dx.hypot(dy);
+            if distance == 0.0 {
+                continue;
+            }
+            let acceleration = attractor.strength / distance.max(MIN_ATTRACTOR_DISTANCE).powi(2);
+            pull.0 += acceleration * dx / distance;
+            pull.1 += acceleration * dy / distance;
+        }
+        pull
+    }
+
+    /// Randomly lets a dying particle flash for the next frame.
+    pub fn crackle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.flashing =
+            !self.is_dead() && self.lifetime < CRACKLE_LIFETIME && rng.gen_bool(CRACKLE_CHANCE);
+    }
+
+    /// Kills the particle once it left the bounds to the side or bottom, see
+    /// [Particle::is_within].
+    pub fn kill_outside(&mut self, width: f64, height: f64) {
+        if !self.is_within(width, height) {
+            self.lifetime = 0.0;
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.lifetime <= 0.0
+    }
+
+    /// Whether the particle is horizontally within `width` and not below
+    /// `height`. Particles above the top may still fall back into view.
+    pub fn is_within(&self, width: f64, height: f64) -> bool {
+        self.position.0 + self.dimensions.0 as f64 > 0.0
+            && self.position.0 < width
+            && self.position.1 <= height
+    }
+}
+
+/// Particles of dead fireworks kept around for reuse, so dense shows don't
+/// allocate new trails all the time.
+#[derive(Default)]
+pub struct ParticlePool {
+    free: Vec<Particle>,
+}
+
+impl ParticlePool {
+    /// A particle as created by [Particle::new], reusing a released one if
+    /// there is any.
+    pub fn acquire(&mut self, x: i64, y: i64, width: u32, height: u32, color: Color) -> Particle {
+        let Some(mut particle) = self.free.pop() else {
+            return Particle::new(x, y, width, height, color);
+        };
+        let mut trail = std::mem::take(&mut particle.trail);
+        trail.clear();
+        Particle {
+            trail,
+            ..Particle::new(x, y, width, height, color)
+        }
+    }
+
+    /// Keeps `particle` around for a later [ParticlePool::acquire].
+    pub fn release(&mut self, particle: Particle) {
+        self.free.push(particle);
+    }
+
+    /// Number of particles ready for reuse.
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+}
+
+/// Buckets items by their position on a uniform grid of square cells, so the
+/// items near a position are found without looking at all of them.
+pub struct SpatialGrid<T> {
+    /// Width and height of a cell in pixels.
+    cell_size: f64,
+    columns: usize,
+    rows: usize,
+    /// Items of every cell, row by row.
+    cells: Vec<Vec<T>>,
+}
+
+impl<T> SpatialGrid<T> {
+    /// An empty grid covering `width` x `height` pixels with cells of
+    /// `cell_size` pixels.
+    pub fn new(width: u32, height: u32, cell_size: f64) -> Self {
+        let cell_size = cell_size.max(1.0);
+        let columns = (width as f64 / cell_size).ceil().max(1.0) as usize;
+        let rows = (height as f64 / cell_size).ceil().max(1.0) as usize;
+        Self {
+            cell_size,
+            columns,
+            rows,
+            cells: (0..columns * rows).map(|_| vec![]).collect(),
+        }
+    }
+
+    /// Adds `item` at `position`. Positions beyond the canvas go into the
+    /// nearest cell on its edge.
+    pub fn insert(&mut self, position: (f64, f64), item: T) {
+        let (column, row) = self.cell_of(position);
+        self.cells[row * self.columns + column].push(item);
+    }
+
+    /// Items in the cell of `position` and the eight cells around it, which
+    /// includes every item closer than the cell size.
+    pub fn neighbors(&self, position: (f64, f64)) -> impl Iterator<Item = &T> {
+        let (column, row) = self.cell_of(position);
+        let columns = column.saturating_sub(1)..(column + 2).min(self.columns);
+        let rows = row.saturating_sub(1)..(row + 2).min(self.rows);
+        rows.flat_map(move |row| {
+            columns
+                .clone()
+                .flat_map(move |column| self.cells[row * self.columns + column].iter())
+        })
+    }
+
+    fn cell_of(&self, position: (f64, f64)) -> (usize, usize) {
+        let cell = |value: f64, count: usize| {
+            ((value / self.cell_size).floor().max(0.0) as usize).min(count - 1)
+        };
+        (cell(position.0, self.columns), cell(position.1, self.rows))
+    }
+}
+
+/// Shape of the burst of a firework.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FireworkKind {
+    /// Sparks sprayed randomly in all directions.
+    Sphere,
+    /// Sparks at evenly spaced angles flying out with equal speed.
+    Ring,
+    /// Long lived sparks drooping down under strong gravity.
+    Willow,
+    /// A few big sparks with long trails shooting upwards like palm fronds.
+    Palm,
+}
+
+/// Names of all firework kinds, as accepted by [FireworkKind::from_name].
+pub const FIREWORK_KIND_NAMES: &[&str] = &["sphere", "ring", "willow", "palm"];
+
+impl FireworkKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sphere" => Some(FireworkKind::Sphere),
+            "ring" => Some(FireworkKind::Ring),
+            "willow" => Some(FireworkKind::Willow),
+            "palm" => Some(FireworkKind::Palm),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FireworkKind::Sphere => "sphere",
+            FireworkKind::Ring => "ring",
+            FireworkKind::Willow => "willow",
+            FireworkKind::Palm => "palm",
+        }
+    }
+}
+
+impl Distribution<FireworkKind> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> FireworkKind {
+        match rng.gen_range(0..4) {
+            0 => FireworkKind::Sphere,
+            1 => FireworkKind::Ring,
+            2 => FireworkKind::Willow,
+            _ => FireworkKind::Palm,
+        }
+    }
+}
+
+/// Whether the rocket of a firework has left the ground yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// The mortar is firing for the given number of frames, flashing at the
+    /// base of the rocket while it stays in place.
+    Charging(u32),
+    /// The rocket is rising, or burst already.
+    Flying,
+}
+
+/// How a rocket fails to burst properly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dud {
+    /// The rocket fades out while rising and never bursts.
+    Fizzle,
+    /// The rocket bursts into a few sparks only.
+    Weak,
+}
+
+pub struct Firework {
+    kind: FireworkKind,
+    phase: Phase,
+    rocket: Option<Particle>,
+    effect: Vec<Particle>,
+    /// Short lived glints left behind by the burst sparks.
+    twinkles: Vec<Particle>,
+    /// Later stages of a multi-stage firework.
+    children: Vec<Firework>,
+    base_color: HslColor,
+    /// Color of the slower sparks near the center of a two tone burst, which
+    /// otherwise all get the base color.
+    inner_color: Option<HslColor>,
+    /// Width and height of the burst sparks in pixels.
+    spark_size: u32,
+    /// Number of previous positions each spark leaves behind.
+    trail_length: usize,
+    /// Whether some sparks of the burst burst again.
+    multistage: bool,
+    /// Whether dying sparks flicker.
+    crackle: bool,
+    /// Degrees the hue of the sparks turns by over their lifetime.
+    cooling: f64,
+    /// Whether the sparks leave twinkles behind.
+    twinkle: bool,
+    /// Whether the rising rocket leaves sparks behind.
+    launch_trail: bool,
+    /// Whether the burst flashes white at its center.
+    core: bool,
+    /// Whether some burst sparks are bigger and last longer than others.
+    vary_size: bool,
+    /// Fewest frames every spark and twinkle lasts.
+    min_spark_frames: u32,
+    /// How much faster sparks are desaturated and dimmed, from 0 to 1.
+    depth_fade: f64,
+    /// Stage of a multi-stage firework, 0 for the launched one.
+    depth: u32,
+    /// Steps since the firework was launched.
+    age: u32,
+    /// Steps after which the firework counts as dead, see [MAX_AGE].
+    max_age: u32,
+    /// Factor applied to the number and speed of the burst sparks.
+    scale: f64,
+    /// Frames until the rocket bursts. Without one it bursts at the apex of
+    /// its flight.
+    fuse: Option<u32>,
+    /// Factor applied to the number of burst sparks.
+    burst_size: f64,
+    /// Factor applied to the number of burst sparks for the canvas size.
+    area_scale: f64,
+    /// Offsets of the sparks of a shaped burst from its center, which
+    /// replace the random spray of its kind.
+    shape: Option<Rc<[(f64, f64)]>>,
+    /// Whether a reflection across the vertical center line of the canvas is
+    /// drawn as well.
+    mirror: bool,
+    /// Largest fraction by which gravity and drag of a spark randomly differ
+    /// from the global ones.
+    gravity_jitter: f64,
+    /// Degrees of the cone around straight up that randomly sprayed sparks
+    /// fly into. Without one they spread roughly like a ball.
+    spread: Option<f64>,
+    /// Factor applied to how far randomly sprayed sparks are thrown upwards
+    /// rather than evenly in all directions, see [Firework::with_rise].
+    rise: f64,
+    dud: Option<Dud>,
+    /// Most particles the firework keeps, counting those of its later
+    /// stages, the dimmest ones making room for new ones. Only set on the
+    /// first stage.
+    max_particles: Option<usize>,
+    /// Random numbers of the firework once launched, apart from those of
+    /// all other fireworks, each later stage getting its own.
+    rng: Xoshiro256PlusPlus,
+}
+
+impl Firework {
+    pub fn new<R: Rng + ?Sized>(
+        rng: &mut R,
+        pool: &mut ParticlePool,
+        kind: FireworkKind,
+        x: i64,
+        y: i64,
+        speed: (f64, f64),
+        effect_color: Color,
+    ) -> Self {
+        Self {
+            kind,
+            phase: Phase::Flying,
+            rocket: Some(
+                pool.acquire(x, y, 1, 3, Color::from_rgb(255, 255, 255))
+                    .with_speed(speed.0, speed.1)
+                    .with_fading(0.0),
+            ),
+            effect: vec![],
+            twinkles: vec![],
+            children: vec![],
+            base_color: effect_color.as_hsl(),
+            inner_color: None,
+            spark_size: 1,
+            trail_length: 0,
+            multistage: false,
+            crackle: false,
+            cooling: 0.0,
+            twinkle: false,
+            launch_trail: false,
+            core: false,
+            vary_size: false,
+            min_spark_frames: 0,
+            depth_fade: 0.0,
+            depth: 0,
+            age: 0,
+            max_age: MAX_AGE,
+            scale: 1.0,
+            fuse: None,
+            burst_size: rng.gen_range(BURST_SIZES),
+            area_scale: 1.0,
+            shape: None,
+            mirror: false,
+            gravity_jitter: 0.0,
+            spread: None,
+            rise: 1.0,
+            dud: None,
+            max_particles: None,
+            rng: Xoshiro256PlusPlus::seed_from_u64(rng.gen()),
+        }
+    }
+
+    pub fn with_multistage(self, multistage: bool) -> Self {
+        Self { multistage, ..self }
+    }
+
+    pub fn with_crackle(self, crackle: bool) -> Self {
+        Self { crackle, ..self }
+    }
+
+    pub fn with_cooling(self, cooling: f64) -> Self {
+        Self { cooling, ..self }
+    }
+
+    pub fn with_twinkle(self, twinkle: bool) -> Self {
+        Self { twinkle, ..self }
+    }
+
+    /// Gives the slower sparks of the burst `inner_color` instead of the base
+    /// color.
+    pub fn with_inner_color(self, inner_color: Option<Color>) -> Self {
+        Self {
+            inner_color: inner_color.map(|color| color.as_hsl()),
+            ..self
+        }
+    }
+
+    pub fn with_min_spark_frames(self, min_spark_frames: u32) -> Self {
+        Self {
+            min_spark_frames,
+            ..self
+        }
+    }
+
+    /// Counts the firework as dead `max_age` steps after its launch, sparks
+    /// still alive by then included.
+    pub fn with_max_age(self, max_age: u32) -> Self {
+        Self { max_age, ..self }
+    }
+
+    /// Lets faster sparks look farther away, `depth_fade` ranging from 0 for
+    /// not at all to 1.
+    pub fn with_depth_fade(self, depth_fade: f64) -> Self {
+        Self { depth_fade, ..self }
+    }
+
+    pub fn with_vary_size(self, vary_size: bool) -> Self {
+        Self { vary_size, ..self }
+    }
+
+    pub fn with_core(self, core: bool) -> Self {
+        Self { core, ..self }
+    }
+
+    /// Lets the mortar flash for `frames` frames before the rocket rises.
+    pub fn with_mortar(self, frames: u32) -> Self {
+        let phase = match frames {
+            0 => Phase::Flying,
+            frames => Phase::Charging(frames),
+        };
+        Self { phase, ..self }
+    }
+
+    /// Scales the burst by the first factor of `size` and its number of
+    /// sparks by the second, instead of by a random factor.
+    pub fn with_size(self, size: Option<(f64, f64)>) -> Self {
+        let Some((scale, burst_size)) = size else {
+            return self;
+        };
+        Self {
+            scale,
+            burst_size,
+            ..self
+        }
+    }
+
+    /// Keeps at most `max` particles, as counted by
+    /// [Firework::total_particles], dropping the dimmest ones first.
+    pub fn with_max_particles(self, max: Option<usize>) -> Self {
+        Self {
+            max_particles: max,
+            ..self
+        }
+    }
+
+    /// Lets the rocket fail as a `dud` instead of bursting properly.
+    pub fn with_dud(self, dud: Option<Dud>) -> Self {
+        let rocket = match dud {
+            Some(Dud::Fizzle) => self.rocket.map(|rocket| rocket.with_fading(FIZZLE_FADING)),
+            _ => self.rocket,
+        };
+        Self {
+            rocket,
+            dud,
+            ..self
+        }
+    }
+
+    /// Tints the rising rocket with a light version of the color it bursts
+    /// into, instead of white.
+    pub fn with_tinted_rocket(self, tinted: bool) -> Self {
+        if !tinted {
+            return self;
+        }
+        let color = HslColor::new(self.base_color.h, self.base_color.s, ROCKET_TINT_LIGHTNESS);
+        Self {
+            rocket: self.rocket.map(|rocket| Particle {
+                color: color.into(),
+                ..rocket
+            }),
+            ..self
+        }
+    }
+
+    /// Lets the burst take a shape given by the offsets of its sparks from
+    /// its center, instead of spraying them as its kind does.
+    pub fn with_shape(self, shape: Option<Rc<[(f64, f64)]>>) -> Self {
+        Self { shape, ..self }
+    }
+
+    /// Also draws the firework reflected across the vertical center line of
+    /// the canvas, spark for spark.
+    pub fn with_mirror(self, mirror: bool) -> Self {
+        Self { mirror, ..self }
+    }
+
+    /// Lets gravity and drag of every spark randomly differ by up to
+    /// `jitter` times the global ones, so bursts deform as they fall.
+    pub fn with_gravity_jitter(self, jitter: f64) -> Self {
+        Self {
+            gravity_jitter: jitter,
+            ..self
+        }
+    }
+
+    /// Scales the upward bias of sparks sprayed without a spread by `rise`.
+    ///
+    /// Spheres and willows normally throw most of their sparks upwards, so
+    /// they arc before falling. A rise of 0 throws them evenly in all
+    /// directions, and from about 1.25 on a sphere throws every spark up
+    /// like a fountain raining down.
+    pub fn with_rise(self, rise: f64) -> Self {
+        Self { rise, ..self }
+    }
+
+    /// Sprays the sparks of spheres and willows into a cone of `degrees`
+    /// around straight up, 360 being a full circle.
+    pub fn with_spread(self, degrees: Option<f64>) -> Self {
+        Self {
+            spread: degrees,
+            ..self
+        }
+    }
+
+    /// Makes the rising rocket `width` x `height` pixels large instead of
+    /// 1 x 3.
+    pub fn with_rocket_size(self, width: u32, height: u32) -> Self {
+        Self {
+            rocket: self
+                .rocket
+                .map(|rocket| rocket.with_dimensions(width, height)),
+            ..self
+        }
+    }
+
+    pub fn with_launch_trail(self, launch_trail: bool) -> Self {
+        Self {
+            launch_trail,
+            ..self
+        }
+    }
+
+    pub fn with_spark_size(self, spark_size: u32) -> Self {
+        Self { spark_size, ..self }
+    }
+
+    pub fn with_trail(self, trail_length: usize) -> Self {
+        Self {
+            trail_length,
+            ..self
+        }
+    }
+
+    /// Scales the number of burst sparks to a canvas of `area` pixels, adding
+    /// `spark_density` sparks per 1000 pixels to a sphere burst. The scale is
+    /// relative to the default canvas, whatever number of sparks the
+    /// simulation gives a sphere.
+    pub fn with_canvas_area(self, area: u32, spark_density: f64) -> Self {
+        let sparks = (BASE_SPARKS + spark_density * area as f64 / 1000.0).min(MAX_SPHERE_SPARKS);
+        Self {
+            area_scale: sparks / SimParams::DEFAULT.sphere_sparks as f64,
+            ..self
+        }
+    }
+
+    /// Creates the burst sparks of this firework's kind at `origin`, as
+    /// many and as fast as the `params` give them.
+    pub fn emit<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        pool: &mut ParticlePool,
+        params: &SimParams,
+        origin: (f64, f64),
+    ) -> Vec<Particle> {
+        let scale = self.scale;
+        let mut sparks: Vec<Particle> = match (&self.shape, self.kind) {
+            (Some(shape), _) => shape
+                .iter()
+                .map(|&(x, y)| {
+                    let color = self.spark_color(rng);
+                    let (x, y) = (SHAPE_SCALE * scale * x, SHAPE_SCALE * scale * y);
+                    // Exactly at the origin instead of jittered, so the
+                    // shape stays sharp.
+                    let spark = pool
+                        .acquire(
+                            origin.0.round() as i64,
+                            origin.1.round() as i64,
+                            self.spark_size,
+                            self.spark_size,
+                            color,
+                        )
+                        .with_trail(self.trail_length)
+                        .with_hue_shift(self.cooling)
+                        .with_min_frames(self.min_spark_frames)
+                        .with_speed(SHAPE_SPEED * x, SHAPE_SPEED * y)
+                        .with_fading(SHAPE_FADING);
+                    self.vary_forces(rng, spark)
+                })
+                .collect(),
+            (None, FireworkKind::Sphere) => (0..self.spark_count(params.sphere_sparks))
+                .map(|_| {
+                    let color = self.spark_color(rng);
+                    let spark = self.spark(rng, pool, params, origin, color);
+                    let (x, y) = self.spray_speed(rng, params.sphere_speed, 0.9);
+                    spark.with_speed(x, y)
+                })
+                .collect(),
+            (None, FireworkKind::Ring) => {
+                let count = self.spark_count(24);
+                (0..count)
+                    .map(|i| {
+                        let angle = i as f64 / count as f64 * TAU;
+                        let color = self.spark_color(rng);
+                        self.spark(rng, pool, params, origin, color)
+                            .with_speed(scale * 0.8 * angle.cos(), scale * 0.8 * angle.sin())
+                    })
+                    .collect()
+            }
+            (None, FireworkKind::Willow) => (0..self.spark_count(30))
+                .map(|_| {
+                    let color = self.spark_color(rng);
+                    let spark = self.spark(rng, pool, params, origin, color);
+                    let (x, y) = self.spray_speed(rng, 1.2, 0.8);
+                    spark
+                        .with_speed(x, y)
+                        .with_fading(0.004)
+                        .with_acceleration(0.0, 0.015)
+                })
+                .collect(),
+            (None, FireworkKind::Palm) => {
+                let count = self.spark_count(7);
+                (0..count)
+                    .map(|i| {
+                        // Evenly spread over the upper half of the circle.
+                        let angle = PI + (i as f64 + 0.5) / count as f64 * PI;
+                        let color = self.spark_color(rng);
+                        self.spark(rng, pool, params, origin, color)
+                            .with_dimensions(self.spark_size + 1, self.spark_size + 1)
+                            .with_trail(self.trail_length.max(5))
+                            .with_speed(scale * 1.3 * angle.cos(), scale * 1.3 * angle.sin())
+                            .with_fading(0.008)
+                    })
+                    .collect()
+            }
+        };
+        if let Some(ref inner_color) = self.inner_color {
+            self.color_inner_sparks(rng, &mut sparks, inner_color);
+        }
+        if self.depth_fade > 0.0 {
+            self.fade_outer_sparks(&mut sparks);
+        }
+        if self.vary_size {
+            vary_sizes(rng, &mut sparks);
+        }
+        sparks
+    }
+
+    /// Gives the slower half of the `sparks`, those staying near the center
+    /// of the burst, a variation of `inner_color`.
+    fn color_inner_sparks<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        sparks: &mut [Particle],
+        inner_color: &HslColor,
+    ) {
+        let speed = |spark: &Particle| spark.speed.0.hypot(spark.speed.1);
+        let fastest = sparks.iter().map(speed).fold(0.0, f64::max);
+        for spark in sparks.iter_mut() {
+            if speed(spark) < fastest * INNER_SPEED {
+                spark.color = vary_color(rng, inner_color);
+            }
+        }
+    }
+
+    /// Desaturates and dims the `sparks` by how fast they fly out compared to
+    /// the fastest one, so the outer ones look farther away.
+    fn fade_outer_sparks(&self, sparks: &mut [Particle]) {
+        let speed = |spark: &Particle| spark.speed.0.hypot(spark.speed.1);
+        let fastest = sparks.iter().map(speed).fold(0.0, f64::max);
+        if fastest == 0.0 {
+            return;
+        }
+        for spark in sparks.iter_mut() {
+            let distance = self.depth_fade * speed(spark) / fastest;
+            let hsl = spark.color.as_hsl();
+            spark.color = HslColor::new(
+                hsl.h,
+                (hsl.s - distance * DEPTH_DESATURATION).max(0.0),
+                (hsl.l - distance * DEPTH_DIMMING).max(0.0),
+            )
+            .into();
+        }
+    }
+
+    /// Slow, quickly fading white sparks flashing at the center of a burst.
+    fn core_sparks<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        pool: &mut ParticlePool,
+        params: &SimParams,
+        origin: (f64, f64),
+    ) -> Vec<Particle> {
+        (0..self.spark_count(CORE_SPARKS))
+            .map(|_| {
+                let angle = rng.gen::<f64>() * TAU;
+                let speed = self.scale * 0.3 * rng.gen::<f64>();
+                self.spark(rng, pool, params, origin, CORE_COLOR)
+                    .with_speed(speed * angle.cos(), speed * angle.sin())
+                    .with_fading(0.05)
+            })
+            .collect()
+    }
+
+    /// Number of sparks for a burst of `full` sparks at this firework's scale,
+    /// burst size and canvas size.
+    fn spark_count(&self, full: usize) -> usize {
+        let factor = self.scale * self.burst_size * self.area_scale;
+        ((full as f64 * factor).round() as usize).max(1)
+    }
+
+    /// Turns a few of the burst `sparks` into rockets of their own, which
+    /// burst again after a short delay.
+    fn split_stages<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        sparks: &mut Vec<Particle>,
+    ) -> Vec<Firework> {
+        let count = SECONDARY_ROCKETS.min(sparks.len());
+        sparks
+            .drain(..count)
+            .map(|spark| Firework {
+                kind: FireworkKind::Sphere,
+                phase: Phase::Flying,
+                rocket: Some(spark.with_fading(0.0)),
+                effect: vec![],
+                twinkles: vec![],
+                children: vec![],
+                base_color: HslColor::new(self.base_color.h, self.base_color.s, self.base_color.l),
+                inner_color: self
+                    .inner_color
+                    .as_ref()
+                    .map(|color| HslColor::new(color.h, color.s, color.l)),
+                spark_size: self.spark_size,
+                trail_length: self.trail_length,
+                multistage: self.multistage,
+                crackle: self.crackle,
+                cooling: self.cooling,
+                twinkle: self.twinkle,
+                launch_trail: false,
+                core: self.core,
+                vary_size: self.vary_size,
+                min_spark_frames: self.min_spark_frames,
+                depth_fade: self.depth_fade,
+                depth: self.depth + 1,
+                age: 0,
+                max_age: self.max_age,
+                scale: self.scale * 0.5,
+                fuse: Some(rng.gen_range(20..40)),
+                burst_size: self.burst_size,
+                area_scale: self.area_scale,
+                shape: None,
+                // Reflected bursts as well, but drawn only by the firework
+                // they burst from, on its sides.
+                mirror: self.mirror,
+                gravity_jitter: self.gravity_jitter,
+                spread: self.spread,
+                rise: self.rise,
+                dud: None,
+                // The first stage keeps the count for all of them.
+                max_particles: None,
+                rng: Xoshiro256PlusPlus::seed_from_u64(rng.gen()),
+            })
+            .collect()
+    }
+
+    /// A burst spark near `origin`, randomly offset so the burst starts as a
+    /// small ball instead of a single pixel.
+    fn spark<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        pool: &mut ParticlePool,
+        params: &SimParams,
+        origin: (f64, f64),
+        color: Color,
+    ) -> Particle {
+        let jitter = SPARK_JITTER * self.scale;
+        let spark = pool
+            .acquire(
+                (origin.0 + rng.gen_range(-jitter..=jitter)).round() as i64,
+                (origin.1 + rng.gen_range(-jitter..=jitter)).round() as i64,
+                self.spark_size,
+                self.spark_size,
+                color,
+            )
+            .with_fading(params.fading)
+            .with_trail(self.trail_length)
+            .with_hue_shift(self.cooling)
+            .with_min_frames(self.min_spark_frames);
+        self.vary_forces(rng, spark)
+    }
+
+    /// Random speed of a sprayed spark, up to `strength` across.
+    ///
+    /// Without a spread, each axis is random on its own, the vertical one
+    /// shifted up so most sparks rise: `lift` is the share of its range
+    /// pointing up, 0.5 being even, and the rise scales how far it is off
+    /// from even. With a spread, the direction is random within the cone and
+    /// the speed random up to half of `strength`.
+    fn spray_speed<R: Rng + ?Sized>(&self, rng: &mut R, strength: f64, lift: f64) -> (f64, f64) {
+        let strength = self.scale * strength;
+        let Some(spread) = self.spread else {
+            return (
+                strength * (rng.gen::<f64>() - 0.5),
+                strength * (rng.gen::<f64>() - 0.5 - (lift - 0.5) * self.rise),
+            );
+        };
+        let half = spread.to_radians() / 2.0;
+        let angle = rng.gen_range(-half..=half);
+        // The square root spreads the sparks evenly over the cone instead of
+        // crowding them at its tip.
+        let speed = strength / 2.0 * rng.gen::<f64>().sqrt();
+        (speed * angle.sin(), -speed * angle.cos())
+    }
+
+    /// Randomly scales gravity and drag of `spark` by the gravity jitter.
+    fn vary_forces<R: Rng + ?Sized>(&self, rng: &mut R, spark: Particle) -> Particle {
+        // Returning before any roll keeps the random sequence of a seed, and
+        // with it every later burst, as it was without jitter.
+        if self.gravity_jitter <= 0.0 {
+            return spark;
+        }
+        let jitter = self.gravity_jitter;
+        spark.with_force_scale(
+            1.0 + rng.gen_range(-jitter..=jitter),
+            1.0 + rng.gen_range(-jitter..=jitter),
+        )
+    }
+
+    fn spark_color<R: Rng + ?Sized>(&self, rng: &mut R) -> Color {
+        vary_color(rng, &self.base_color)
+    }
+
+    /// Vertical launch speed needed for a rocket to burst `height` pixels
+    /// above its starting point.
+    pub fn launch_speed(height: f64, gravity: f64) -> f64 {
+        -(2.0 * gravity * height.max(0.0)).sqrt()
+    }
+
+    pub fn draw<C: Canvas>(&self, canvas: &mut C, style: &DrawStyle) {
+        self.draw_side(canvas, style, false);
+        if self.mirror {
+            self.draw_side(canvas, style, true);
+        }
+    }
+
+    /// Draws the firework, reflected across the vertical center line of the
+    /// canvas if `mirrored`.
+    fn draw_side<C: Canvas>(&self, canvas: &mut C, style: &DrawStyle, mirrored: bool) {
+        if let Some(ref rocket) = self.rocket {
+            match self.phase {
+                Phase::Charging(frames) => draw_mortar(canvas, rocket, frames, mirrored),
+                Phase::Flying => rocket.draw_side(canvas, style, mirrored),
+            }
+        }
+
+        for particle in self.effect.iter().chain(self.twinkles.iter()) {
+            particle.draw_side(canvas, style, mirrored);
+        }
+
+        // Stages are drawn on the same sides as the firework they burst
+        // from.
+        for child in self.children.iter() {
+            child.draw_side(canvas, style, mirrored);
+        }
+    }
+
+    /// Number of times the firework is in the sky, two with its reflection.
+    pub fn copies(&self) -> usize {
+        if self.mirror {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Advances the firework by one frame under the `params`, with sparks
+    /// pulled by the `attractors`, adding any rockets which burst to
+    /// `bursts`.
+    pub fn update(
+        &mut self,
+        pool: &mut ParticlePool,
+        params: &SimParams,
+        attractors: &[Attractor],
+        bursts: &mut Vec<Burst>,
+    ) {
+        // Moved out for the update, which borrows the rest of the firework.
+        let mut rng = self.rng.clone();
+        self.advance(&mut rng, pool, params, attractors, bursts);
+        self.rng = rng;
+    }
+
+    /// [Firework::update] drawing its random numbers from `rng`.
+    fn advance<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        pool: &mut ParticlePool,
+        params: &SimParams,
+        attractors: &[Attractor],
+        bursts: &mut Vec<Burst>,
+    ) {
+        let (forces, edges) = (params.forces(attractors), params.edges());
+        self.age = self.age.saturating_add(1);
+        if let Phase::Charging(frames) = self.phase {
+            self.phase = match frames {
+                1 => Phase::Flying,
+                frames => Phase::Charging(frames - 1),
+            };
+            return;
+        }
+
+        if let Some(ref mut rocket) = self.rocket {
+            let rising = rocket.speed.1 < 0.0;
+            // Rockets are heavy enough to ignore wind, drag and attractors.
+            rocket.update(
+                &Forces {
+                    wind: 0.0,
+                    drag: 0.0,
+                    attractors: &[],
+                    ..forces
+                },
+                &Edges::NONE,
+            );
+            if self.launch_trail {
+                let (x, y) = rocket.position;
+                let exhaust = Particle {
+                    exhaust: true,
+                    ..pool
+                        .acquire(
+                            x.round() as i64,
+                            (y + rocket.dimensions.1 as f64).round() as i64,
+                            1,
+                            1,
+                            LAUNCH_TRAIL_COLOR,
+                        )
+                        .with_speed(0.2 * (rng.gen::<f64>() - 0.5), 0.2 + 0.2 * rng.gen::<f64>())
+                        .with_fading(LAUNCH_TRAIL_FADING)
+                };
+                self.effect.push(exhaust);
+            }
+            let burst = match self.fuse {
+                Some(ref mut frames) => {
+                    *frames = frames.saturating_sub(1);
+                    *frames == 0
+                }
+                // The apex is where the vertical speed turns from rising to
+                // falling.
+                None => rising && rocket.speed.1 >= 0.0,
+            };
+            if self.dud == Some(Dud::Fizzle) {
+                // Burnt out or not, it peaks without bursting.
+                if burst || rocket.is_dead() {
+                    if let Some(rocket) = self.rocket.take() {
+                        pool.release(rocket);
+                    }
+                }
+            } else if burst {
+                let origin = rocket.position;
+                let rocket_width = rocket.dimensions.0 as f64;
+                let mut sparks = self.emit(rng, pool, params, origin);
+                if self.dud == Some(Dud::Weak) {
+                    for spark in sparks.drain(WEAK_DUD_SPARKS.min(sparks.len())..) {
+                        pool.release(spark);
+                    }
+                } else if self.core {
+                    sparks.extend(self.core_sparks(rng, pool, params, origin));
+                }
+                let burst = Burst {
+                    position: origin,
+                    color: HslColor::new(self.base_color.h, self.base_color.s, self.base_color.l)
+                        .into(),
+                    sparks: sparks.len(),
+                };
+                bursts.push(burst);
+                if self.mirror {
+                    // The reflection bursts just the same.
+                    let x = params.width as f64 - origin.0 - rocket_width;
+                    bursts.push(Burst {
+                        position: (x, origin.1),
+                        ..burst
+                    });
+                }
+                if self.multistage && self.depth < MAX_STAGE_DEPTH && self.dud.is_none() {
+                    let stages = self.split_stages(rng, &mut sparks);
+                    self.children.extend(stages);
+                }
+                self.effect.extend(sparks);
+                if let Some(rocket) = self.rocket.take() {
+                    pool.release(rocket);
+                }
+            }
+        }
+
+        for particle in self.effect.iter_mut() {
+            particle.update(&forces, &edges);
+            if self.crackle && !particle.exhaust {
+                particle.crackle(rng);
+            }
+        }
+
+        if self.twinkle {
+            self.update_twinkles(rng, pool, &forces, &edges);
+        }
+
+        for child in self.children.iter_mut() {
+            child.update(pool, params, attractors, bursts);
+        }
+
+        if let Some(max) = self.max_particles {
+            self.evict_dimmest(max, pool);
+        }
+    }
+
+    /// Moves the twinkles and lets the live sparks leave new ones behind.
+    fn update_twinkles<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        pool: &mut ParticlePool,
+        forces: &Forces,
+        edges: &Edges,
+    ) {
+        for twinkle in self.twinkles.extract_if(.., |twinkle| twinkle.is_dead()) {
+            pool.release(twinkle);
+        }
+        for twinkle in self.twinkles.iter_mut() {
+            twinkle.update(forces, edges);
+        }
+
+        let white = Color::from_rgb(255, 255, 255);
+        for spark in self
+            .effect
+            .iter()
+            .filter(|spark| !spark.is_dead() && !spark.exhaust)
+        {
+            if !rng.gen_bool(TWINKLE_CHANCE) {
+                continue;
+            }
+            let twinkle = pool
+                .acquire(
+                    spark.position.0.round() as i64,
+                    spark.position.1.round() as i64,
+                    1,
+                    1,
+                    Color::blend(&spark.color, &white, 0.5),
+                )
+                .with_speed(
+                    0.2 * (rng.gen::<f64>() - 0.5),
+                    0.2 * (rng.gen::<f64>() - 0.5),
+                )
+                .with_fading(TWINKLE_FADING)
+                .with_min_frames(self.min_spark_frames);
+            self.twinkles.push(twinkle);
+        }
+    }
+
+    /// Releases the particles with the least lifetime left until at most
+    /// `max` remain in the firework and its later stages, dead ones going
+    /// first. A rising rocket of the first stage counts as one and is always
+    /// kept, as it has nothing to burst from yet.
+    fn evict_dimmest(&mut self, max: usize, pool: &mut ParticlePool) {
+        let mut lifetimes = vec![];
+        self.collect_lifetimes(&mut lifetimes);
+        let kept = max.saturating_sub(self.rocket.iter().count());
+        let Some(excess) = lifetimes
+            .len()
+            .checked_sub(kept)
+            .filter(|&excess| excess > 0)
+        else {
+            return;
+        };
+        let (_, &mut threshold, _) = lifetimes.select_nth_unstable_by(excess - 1, f64::total_cmp);
+        // Of the particles exactly at the threshold only as many go as needed.
+        let mut ties = excess - lifetimes.iter().filter(|&&l| l < threshold).count();
+        self.evict_below(threshold, &mut ties, pool);
+    }
+
+    /// Lifetimes of all sparks and twinkles, and of the rockets of later
+    /// stages.
+    fn collect_lifetimes(&self, lifetimes: &mut Vec<f64>) {
+        let particles = self.effect.iter().chain(&self.twinkles);
+        lifetimes.extend(particles.map(|particle| particle.lifetime));
+        for child in &self.children {
+            lifetimes.extend(child.rocket.iter().map(|rocket| rocket.lifetime));
+            child.collect_lifetimes(lifetimes);
+        }
+    }
+
+    /// Releases the particles found by [Firework::collect_lifetimes] with
+    /// less lifetime left than `threshold`, and `ties` of those with exactly
+    /// as much.
+    fn evict_below(&mut self, threshold: f64, ties: &mut usize, pool: &mut ParticlePool) {
+        let evicted = |particle: &Particle, ties: &mut usize| {
+            if particle.lifetime == threshold && *ties > 0 {
+                *ties -= 1;
+                return true;
+            }
+            particle.lifetime < threshold
+        };
+        for particles in [&mut self.effect, &mut self.twinkles] {
+            for particle in particles.extract_if(.., |particle| evicted(particle, ties)) {
+                pool.release(particle);
+            }
+        }
+        for child in self.children.iter_mut() {
+            if let Some(rocket) = child.rocket.take_if(|rocket| evicted(rocket, ties)) {
+                pool.release(rocket);
+            }
+            child.evict_below(threshold, ties, pool);
+        }
+    }
+
+    /// Hands all particles of the firework over to the `pool`.
+    pub fn recycle(self, pool: &mut ParticlePool) {
+        let particles = self.rocket.into_iter().chain(self.effect);
+        for particle in particles.chain(self.twinkles) {
+            pool.release(particle);
+        }
+        for child in self.children {
+            child.recycle(pool);
+        }
+    }
+
+    /// Number of live particles, counting a rising rocket as one.
+    pub fn total_particles(&self) -> usize {
+        self.rocket.iter().count()
+            + self.effect.iter().filter(|p| !p.is_dead()).count()
+            + self.twinkles.iter().filter(|p| !p.is_dead()).count()
+            + self
+                .children
+                .iter()
+                .map(Firework::total_particles)
+                .sum::<usize>()
+    }
+
+    /// Kills all sparks which left the given bounds, see
+    /// [Particle::kill_outside]. Rising rockets are kept.
+    pub fn kill_outside(&mut self, width: f64, height: f64) {
+        for particle in self.effect.iter_mut().chain(self.twinkles.iter_mut()) {
+            particle.kill_outside(width, height);
+        }
+        for child in self.children.iter_mut() {
+            child.kill_outside(width, height);
+        }
+    }
+
+    /// Whether any live particle of the firework is within the given bounds,
+    /// see [Particle::is_within].
+    pub fn is_within(&self, width: f64, height: f64) -> bool {
+        self.rocket
+            .iter()
+            .chain(self.effect.iter().filter(|p| !p.is_dead()))
+            .any(|p| p.is_within(width, height))
+            || self.children.iter().any(|c| c.is_within(width, height))
+    }
+
+    /// Whether all particles of the firework died, or it got too old.
+    pub fn is_dead(&self) -> bool {
+        if self.age >= self.max_age {
+            return true;
+        }
+        self.rocket.is_none()
+            && self.effect.iter().all(|effect| effect.is_dead())
+            && self.twinkles.iter().all(|twinkle| twinkle.is_dead())
+            && self.children.iter().all(Firework::is_dead)
+    }
+
+    /// Positions of all live particles, the rising rocket first.
+    #[cfg(test)]
+    pub fn positions(&self) -> Vec<(f64, f64)> {
+        let particles = self.rocket.iter().chain(&self.effect).chain(&self.twinkles);
+        let mut positions: Vec<(f64, f64)> = particles
+            .filter(|particle| !particle.is_dead())
+            .map(|particle| particle.position)
+            .collect();
+        for child in &self.children {
+            positions.extend(child.positions());
+        }
+        positions
+    }
+}
+
+/// Draws the flash of a firing mortar just above the `rocket` waiting to
+/// rise, pulsing with the `frames` left until it does. It is reflected
+/// across the vertical center line of the canvas if `mirrored`.
+fn draw_mortar<C: Canvas>(canvas: &mut C, rocket: &Particle, frames: u32, mirrored: bool) {
+    let pulse = 0.5 + 0.5 * (TAU * frames as f64 / MORTAR_PULSE_FRAMES).cos();
+    let color = Color::blend(&Color::from_rgb(0, 0, 0), &MORTAR_COLOR, 0.4 + 0.6 * pulse);
+    let (mut x, y) = rocket.position;
+    if mirrored {
+        x = canvas.width() as f64 - x - rocket.dimensions.0 as f64;
+    }
+    canvas::fill_rect(
+        canvas,
+        x.round() as i64,
+        y.round() as i64 - 1,
+        rocket.dimensions.0,
+        1,
+        &color,
+    );
+}
+
+/// A random variation in saturation and lightness of `base`.
+fn vary_color<R: Rng + ?Sized>(rng: &mut R, base: &HslColor) -> Color {
+    HslColor::new(
+        base.h,
+        (base.s + (rng.gen::<f64>() - 0.5) * 2.0 * 20.0).clamp(0.0, 100.0),
+        (base.l + (rng.gen::<f64>() - 0.5) * 2.0 * 40.0).clamp(0.0, 100.0),
+    )
+    .into()
+}
+
+/// Randomly makes some of the `sparks` bigger and longer lasting, as picked
+/// from [VARIED_SIZES].
+fn vary_sizes<R: Rng + ?Sized>(rng: &mut R, sparks: &mut [Particle]) {
+    let total: u32 = VARIED_SIZES.iter().map(|&(_, weight, _)| weight).sum();
+    for spark in sparks.iter_mut() {
+        let mut pick = rng.gen_range(0..total);
+        for ((width, height), weight, lifetime) in VARIED_SIZES {
+            if pick < weight {
+                spark.dimensions.0 += width;
+                spark.dimensions.1 += height;
+                spark.fading /= lifetime;
+                break;
+            }
+            pick -= weight;
+        }
+    }
+}
+
+/// Keeps the pixels in memory without ever showing them, for running the
+/// show headless, like the benchmark does.
+pub struct NoopCanvas {
+    width: u32,
+    height: u32,
+    buffer: Vec<Color>,
+}
+
+impl NoopCanvas {
+    /// Creates a black canvas of `width` x `height` pixels.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            buffer: vec![Color::from_rgb(0, 0, 0); (width * height) as usize],
+        }
+    }
+
+    /// The pixels of the last frame, row by row.
+    pub fn pixels(&self) -> &[Color] {
+        &self.buffer
+    }
+}
+
+impl Canvas for NoopCanvas {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn get_range(&self, range: Range<usize>) -> &[Color] {
+        &self.buffer[range]
+    }
+
+    fn set_range(&mut self, range: Range<usize>, colors: &[Color]) {
+        self.buffer[range].copy_from_slice(colors);
+    }
+}
+
+impl RenderableCanvas for NoopCanvas {
+    fn render(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn physical_pos_to_canvas_pos(&self, _x: f64, _y: f64) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn resize_surface(&mut self, width: u32, height: u32) {
+        self.resize(width, height);
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        *self = Self::new(width, height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pixel_loop::rand::SeedableRng;
+    use pixel_loop::rand_xoshiro::Xoshiro256PlusPlus;
+
+    fn rng() -> Xoshiro256PlusPlus {
+        Xoshiro256PlusPlus::seed_from_u64(7)
+    }
+
+    /// A red sphere without any random size, rising from the bottom of the
+    /// default canvas.
+    fn sphere(rng: &mut Xoshiro256PlusPlus, pool: &mut ParticlePool) -> Firework {
+        let color = Color::from_rgb(255, 0, 0);
+        Firework::new(rng, pool, FireworkKind::Sphere, 40, 48, (0.0, -1.0), color)
+            .with_size(Some((1.0, 1.0)))
+    }
+
+    /// Updates `firework` under the default [SimParams] until it dies.
+    fn burn(mut firework: Firework, pool: &mut ParticlePool) -> Firework {
+        while !firework.is_dead() {
+            firework.update(pool, &SimParams::DEFAULT, &[], &mut vec![]);
+        }
+        firework
+    }
+
+    #[test]
+    fn pool_stops_growing_once_warm() {
+        let mut pool = ParticlePool::default();
+        let mut released = vec![];
+        for _ in 0..3 {
+            let mut rng = rng();
+            let firework = sphere(&mut rng, &mut pool);
+            burn(firework, &mut pool).recycle(&mut pool);
+            released.push(pool.available());
+        }
+        // The same firework again takes all its particles from the pool.
+        assert!(released[0] > 25);
+        assert_eq!(released, vec![released[0]; 3]);
+    }
+
+    #[test]
+    fn cap_holds_all_stages() {
+        let (mut rng, mut pool) = (rng(), ParticlePool::default());
+        let mut firework = sphere(&mut rng, &mut pool)
+            .with_multistage(true)
+            .with_twinkle(true)
+            .with_trail(8)
+            .with_launch_trail(true)
+            .with_core(true)
+            .with_max_particles(Some(30));
+        let mut peak = 0;
+        while !firework.is_dead() {
+            firework.update(&mut pool, &SimParams::DEFAULT, &[], &mut vec![]);
+            assert!(firework.total_particles() <= 30);
+            peak = peak.max(firework.total_particles());
+        }
+        assert_eq!(peak, 30);
+    }
+
+    #[test]
+    fn unfading_sparks_die_of_age() {
+        let (mut rng, mut pool) = (rng(), ParticlePool::default());
+        let mut firework = sphere(&mut rng, &mut pool);
+        let params = SimParams {
+            fading: 0.0,
+            boundary: Boundary::Wrap,
+            ..SimParams::DEFAULT
+        };
+        let mut steps = 0;
+        while !firework.is_dead() {
+            firework.update(&mut pool, &params, &[], &mut vec![]);
+            steps += 1;
+        }
+        assert_eq!(steps, MAX_AGE);
+        assert!(firework.total_particles() > 0);
+
+        let mut firework = sphere(&mut rng, &mut pool).with_max_age(100);
+        for _ in 0..99 {
+            firework.update(&mut pool, &params, &[], &mut vec![]);
+        }
+        assert!(!firework.is_dead());
+        firework.update(&mut pool, &params, &[], &mut vec![]);
+        assert!(firework.is_dead());
+    }
+
+    /// A spark at column `x` of the default canvas after moving `speed`
+    /// pixels sideways into the `boundary`.
+    fn cross(boundary: Boundary, x: i64, speed: f64) -> Particle {
+        let params = SimParams {
+            gravity: 0.0,
+            drag: 0.0,
+            boundary,
+            ..SimParams::DEFAULT
+        };
+        let mut spark =
+            Particle::new(x, 10, 1, 1, Color::from_rgb(255, 0, 0)).with_speed(speed, 0.0);
+        spark.update(&params.forces(&[]), &params.edges());
+        spark
+    }
+
+    #[test]
+    fn cull_kills_sparks_leaving_the_sides() {
+        assert!(cross(Boundary::Cull, 0, -2.0).is_dead());
+        assert!(cross(Boundary::Cull, 79, 2.0).is_dead());
+        assert!(!cross(Boundary::Cull, 78, 1.0).is_dead());
+    }
+
+    #[test]
+    fn wrap_moves_sparks_to_the_other_side() {
+        let left = cross(Boundary::Wrap, 0, -2.0);
+        assert_eq!(left.position.0, 78.0);
+        assert!(!left.is_dead());
+        let right = cross(Boundary::Wrap, 79, 2.0);
+        assert_eq!(right.position.0, 1.0);
+        assert!(!right.is_dead());
+    }
+
+    #[test]
+    fn clamp_stops_sparks_at_the_sides() {
+        let left = cross(Boundary::Clamp, 0, -2.0);
+        assert_eq!((left.position.0, left.speed.0), (0.0, 0.0));
+        let right = cross(Boundary::Clamp, 79, 2.0);
+        assert_eq!((right.position.0, right.speed.0), (79.0, 0.0));
+        assert!(!left.is_dead() && !right.is_dead());
+    }
+
+    #[test]
+    fn sparks_are_clipped_to_the_canvas() {
+        let black = Color::from_rgb(0, 0, 0);
+        let style = DrawStyle {
+            sky: canvas::Sky::plain(black),
+            additive: false,
+            fade_curve: FadeCurve::Linear,
+            motion_blur: None,
+            smooth: false,
+        };
+        let mut canvas = NoopCanvas::new(4, 4);
+        Particle::new(-1, -1, 2, 2, Color::from_rgb(255, 0, 0)).draw(&mut canvas, &style);
+        let lit: Vec<usize> = (0..16).filter(|&i| canvas.pixels()[i] != black).collect();
+        assert_eq!(lit, vec![0]);
+    }
+
+    #[test]
+    fn fade_curves() {
+        let at = |curve| [0.0, 0.5, 1.0].map(|lifetime| brightness(lifetime, curve));
+        assert_eq!(at(FadeCurve::Linear), [0.0, 0.5, 1.0]);
+        assert_eq!(at(FadeCurve::EaseOut), [0.0, 0.75, 1.0]);
+        assert_eq!(at(FadeCurve::Quadratic), [0.0, 0.25, 1.0]);
+        assert_eq!(at(FadeCurve::Solid), [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn sphere_bursts_into_25_sparks_at_its_peak() {
+        let (mut rng, mut pool) = (rng(), ParticlePool::default());
+        let mut firework = sphere(&mut rng, &mut pool);
+        let mut steps = 0;
+        while firework.rocket.is_some() {
+            assert!(firework.effect.is_empty());
+            firework.update(&mut pool, &SimParams::DEFAULT, &[], &mut vec![]);
+            steps += 1;
+        }
+        // Rising at 1 pixel per step, gravity stops the rocket after 50.
+        assert_eq!(steps, 50);
+        assert_eq!(firework.effect.len(), 25);
+        assert_eq!(firework.total_particles(), 25);
+    }
+
+    #[test]
+    fn counts_the_rocket_and_its_sparks() {
+        let (mut rng, mut pool) = (rng(), ParticlePool::default());
+        let mut firework = sphere(&mut rng, &mut pool);
+        for _ in 0..49 {
+            firework.update(&mut pool, &SimParams::DEFAULT, &[], &mut vec![]);
+        }
+        assert_eq!(firework.total_particles(), 1);
+        // Sparks of an earlier burst next to the rocket about to burst.
+        let sparks = firework.emit(&mut rng, &mut pool, &SimParams::DEFAULT, (40.0, 10.0));
+        firework.effect.extend(sparks);
+        assert_eq!(firework.total_particles(), 1 + 25);
+        firework.update(&mut pool, &SimParams::DEFAULT, &[], &mut vec![]);
+        assert!(firework.rocket.is_none());
+        assert_eq!(firework.total_particles(), 25 + 25);
+        firework.effect[0].lifetime = 0.0;
+        assert_eq!(firework.total_particles(), 25 + 24);
+    }
+
+    #[test]
+    fn gravity_adds_to_the_falling_speed() {
+        let params = SimParams {
+            gravity: 0.03,
+            drag: 0.0,
+            ..SimParams::DEFAULT
+        };
+        let mut spark =
+            Particle::new(40, 10, 1, 1, Color::from_rgb(255, 0, 0)).with_speed(0.5, -1.0);
+        for _ in 0..10 {
+            let before = spark.speed;
+            spark.update(&params.forces(&[]), &Edges::NONE);
+            assert_eq!(spark.speed, (before.0, before.1 + 0.03));
+        }
+    }
+
+    #[test]
+    fn drag_slows_sparks_down() {
+        let params = SimParams {
+            gravity: 0.0,
+            drag: 0.05,
+            ..SimParams::DEFAULT
+        };
+        let mut spark =
+            Particle::new(40, 10, 1, 1, Color::from_rgb(255, 0, 0)).with_speed(1.0, -1.0);
+        for _ in 0..50 {
+            let before = spark.speed;
+            spark.update(&params.forces(&[]), &Edges::NONE);
+            assert!(spark.speed.0 > 0.0 && spark.speed.0 < before.0);
+            assert!(spark.speed.1 < 0.0 && spark.speed.1 > before.1);
+        }
+    }
+
+    #[test]
+    fn sparks_keep_the_base_lightness() {
+        let (mut rng, mut pool) = (rng(), ParticlePool::default());
+        // Fully saturated, but only half as light.
+        let color = HslColor::new(200.0, 100.0, 50.0).into();
+        let firework = Firework::new(
+            &mut rng,
+            &mut pool,
+            FireworkKind::Sphere,
+            40,
+            48,
+            (0.0, -1.0),
+            color,
+        )
+        .with_size(Some((1.0, 1.0)));
+        let params = SimParams {
+            sphere_sparks: 400,
+            ..SimParams::DEFAULT
+        };
+        let sparks = firework.emit(&mut rng, &mut pool, &params, (40.0, 10.0));
+        let lightness: f64 = sparks
+            .iter()
+            .map(|spark| spark.color.as_hsl().l)
+            .sum::<f64>()
+            / sparks.len() as f64;
+        assert!((lightness - 50.0).abs() < 5.0, "{lightness}");
+    }
+
+    #[test]
+    fn depth_desaturates_faster_sparks() {
+        let (mut rng, mut pool) = (rng(), ParticlePool::default());
+        let color = HslColor::new(200.0, 60.0, 50.0).into();
+        let firework = Firework::new(
+            &mut rng,
+            &mut pool,
+            FireworkKind::Sphere,
+            40,
+            48,
+            (0.0, -1.0),
+            color,
+        )
+        .with_size(Some((1.0, 1.0)))
+        .with_depth_fade(1.0);
+        let params = SimParams {
+            sphere_sparks: 400,
+            ..SimParams::DEFAULT
+        };
+        let mut sparks = firework.emit(&mut rng, &mut pool, &params, (40.0, 10.0));
+        let speed = |spark: &Particle| spark.speed.0.hypot(spark.speed.1);
+        sparks.sort_by(|a, b| speed(a).total_cmp(&speed(b)));
+        let saturation = |sparks: &[Particle]| {
+            sparks
+                .iter()
+                .map(|spark| spark.color.as_hsl().s)
+                .sum::<f64>()
+                / sparks.len() as f64
+        };
+        let (slower, faster) = sparks.split_at(sparks.len() / 2);
+        assert!(saturation(faster) + 5.0 < saturation(slower));
+    }
+
+    #[test]
+    fn reflections_burst_as_well() {
+        let (mut rng, mut pool) = (rng(), ParticlePool::default());
+        let mut firework = sphere(&mut rng, &mut pool).with_mirror(true);
+        let mut bursts = vec![];
+        while bursts.is_empty() {
+            firework.update(&mut pool, &SimParams::DEFAULT, &[], &mut bursts);
+        }
+        let [burst, reflection] = bursts[..] else {
+            panic!("expected a burst and its reflection, got {bursts:?}");
+        };
+        assert_eq!(burst.position.0, 40.0);
+        assert_eq!(reflection.position, (39.0, burst.position.1));
+        assert_eq!(reflection.sparks, burst.sparks);
+    }
+
+    #[test]
+    fn reflected_stages_are_drawn_twice() {
+        let (mut rng, mut pool) = (rng(), ParticlePool::default());
+        let color = Color::from_rgb(255, 0, 0);
+        let mut firework = Firework::new(
+            &mut rng,
+            &mut pool,
+            FireworkKind::Sphere,
+            20,
+            48,
+            (0.0, -1.0),
+            color,
+        )
+        .with_size(Some((1.0, 1.0)))
+        .with_multistage(true)
+        .with_mirror(true);
+        while firework.children.is_empty() {
+            firework.update(&mut pool, &SimParams::DEFAULT, &[], &mut vec![]);
+        }
+        // Leaves only the rockets of the later stages.
+        for spark in firework.effect.drain(..) {
+            pool.release(spark);
+        }
+
+        let black = Color::from_rgb(0, 0, 0);
+        let style = DrawStyle {
+            sky: canvas::Sky::plain(black),
+            additive: true,
+            fade_curve: FadeCurve::Linear,
+            motion_blur: None,
+            smooth: false,
+        };
+        let mut one_side = NoopCanvas::new(80, 48);
+        firework.draw_side(&mut one_side, &style, false);
+        let mut both_sides = NoopCanvas::new(80, 48);
+        firework.draw(&mut both_sides, &style);
+
+        let lit = |canvas: &NoopCanvas| canvas.pixels().iter().filter(|&&p| p != black).count();
+        assert!(lit(&one_side) > 0);
+        assert_eq!(lit(&both_sides), 2 * lit(&one_side));
+        // Drawing any stage a second time on the same side would brighten it.
+        for (i, &pixel) in one_side.pixels().iter().enumerate() {
+            if pixel != black {
+                let reflection = i - i % 80 + 79 - i % 80;
+                assert_eq!(both_sides.pixels()[i], pixel);
+                assert_eq!(both_sides.pixels()[reflection], pixel);
+            }
+        }
+    }
+
+    #[test]
+    fn custom_params_change_the_burst() {
+        let (mut rng, mut pool) = (rng(), ParticlePool::default());
+        let firework = sphere(&mut rng, &mut pool);
+        let params = SimParams {
+            sphere_sparks: 40,
+            sphere_speed: 3.0,
+            fading: 0.05,
+            ..SimParams::DEFAULT
+        };
+        let sparks = firework.emit(&mut rng, &mut pool, &params, (40.0, 10.0));
+        assert_eq!(sparks.len(), 40);
+        // The default speed keeps every spark below 0.75 sideways.
+        assert!(sparks.iter().any(|spark| spark.speed.0.abs() > 0.75));
+        assert!(sparks.iter().all(|spark| spark.fading == 0.05));
+    }
+
+    #[test]
+    fn custom_gravity_pulls_the_rocket() {
+        let (mut rng, mut pool) = (rng(), ParticlePool::default());
+        for gravity in [0.02, 0.05] {
+            let mut firework = sphere(&mut rng, &mut pool);
+            let params = SimParams {
+                gravity,
+                ..SimParams::DEFAULT
+            };
+            firework.update(&mut pool, &params, &[], &mut vec![]);
+            let rocket = firework.rocket.as_ref().unwrap();
+            assert_eq!(rocket.speed.1, -1.0 + gravity);
+        }
+    }
+}
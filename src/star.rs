@@ -0,0 +1,57 @@
+//! Faint stars fixed in the sky behind the show.
+
+use pixel_loop::canvas::Canvas;
+use pixel_loop::color::Color;
+use pixel_loop::rand::Rng;
+use std::f64::consts::TAU;
+use std::ops::Range;
+
+use crate::canvas::Sky;
+use crate::color::Blend;
+
+/// Color of the brightest stars, blended into the sky by their brightness.
+const COLOR: Color = Color::from_rgb(255, 255, 240);
+/// Dimmest and brightest a star is, 1 being the full star color. Kept low
+/// so stars never compete with sparks.
+const BRIGHTNESS: Range<f64> = 0.15..0.35;
+/// Share of its brightness a star loses at the dimmest point of a twinkle.
+const TWINKLE_DEPTH: f64 = 0.4;
+/// Slowest and fastest a star twinkles, in full twinkles per step.
+const TWINKLE_RATES: Range<f64> = 0.002..0.01;
+
+pub struct Star {
+    /// Position as a fraction of the canvas size, so the star keeps its part
+    /// of the sky when the terminal is resized.
+    position: (f64, f64),
+    brightness: f64,
+    /// Twinkles per step.
+    rate: f64,
+    /// Point of the twinkle the star starts at, from 0 to 1.
+    phase: f64,
+}
+
+impl Star {
+    /// A star at a random position in the sky.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self {
+            position: (rng.gen(), rng.gen()),
+            brightness: rng.gen_range(BRIGHTNESS),
+            rate: rng.gen_range(TWINKLE_RATES),
+            phase: rng.gen(),
+        }
+    }
+
+    /// Draws the star onto the `sky` as it twinkles at simulation step
+    /// `frame`.
+    pub fn draw<C: Canvas>(&self, canvas: &mut C, sky: &Sky, frame: u64) {
+        let x = (self.position.0 * canvas.width() as f64) as u32;
+        let y = (self.position.1 * canvas.height() as f64) as u32;
+        if x >= canvas.width() || y >= canvas.height() {
+            return;
+        }
+        let twinkle = (TAU * (self.phase + self.rate * frame as f64)).sin() * 0.5 + 0.5;
+        let brightness = self.brightness * (1.0 - TWINKLE_DEPTH * twinkle);
+        let background = sky.at(y as i64, canvas.height());
+        canvas.set(x, y, &Color::blend(&background, &COLOR, brightness));
+    }
+}
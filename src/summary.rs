@@ -0,0 +1,32 @@
+//! Summary of a show written when it ends, for comparing runs.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Default, Serialize)]
+pub struct RunSummary {
+    pub fireworks_launched: u64,
+    pub frames_rendered: u64,
+    pub peak_particles: usize,
+    pub average_fps: f64,
+    pub duration_secs: f64,
+}
+
+impl RunSummary {
+    /// Fills in the duration of a run and the frame rate over it.
+    pub fn finish(&mut self, duration: Duration) {
+        self.duration_secs = duration.as_secs_f64();
+        if self.duration_secs > 0.0 {
+            self.average_fps = self.frames_rendered as f64 / self.duration_secs;
+        }
+    }
+
+    /// Writes the summary as JSON to `path`.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")
+            .with_context(|| format!("write summary `{}`", path.display()))
+    }
+}
@@ -0,0 +1,83 @@
+//! Asking the terminal for its background color, so the sky matches it.
+//!
+//! The color is queried with the OSC 11 escape sequence, followed by a
+//! request for the device attributes (DA1). Nearly every terminal answers the
+//! latter, so reading the replies ends right away even on terminals which
+//! don't report their colors, instead of only after the timeout.
+
+use crossterm::terminal;
+use pixel_loop::color::Color;
+use std::io::Write as _;
+use std::time::{Duration, Instant};
+
+/// Longest the terminal gets to reply before the sky stays black.
+pub const QUERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The background color of the terminal, if it reports one within the
+/// `timeout`.
+pub fn background(timeout: Duration) -> Option<Color> {
+    terminal::enable_raw_mode().ok()?;
+    let reply = read_reply(timeout);
+    let _ = terminal::disable_raw_mode();
+    parse_reply(&reply?)
+}
+
+/// Sends the queries and collects the replies until the device attributes
+/// arrived, giving up after the `timeout`.
+#[cfg(unix)]
+fn read_reply(timeout: Duration) -> Option<String> {
+    use rustix::event::{poll, PollFd, PollFlags};
+
+    let mut stdout = std::io::stdout();
+    stdout.write_all(b"\x1b]11;?\x1b\\\x1b[c").ok()?;
+    stdout.flush().ok()?;
+
+    let stdin = std::io::stdin();
+    let deadline = Instant::now() + timeout;
+    let mut reply = vec![];
+    // The device attributes come last, ending in a `c`.
+    while !(reply.ends_with(b"c") && reply.windows(3).any(|window| window == b"\x1b[?")) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let mut fds = [PollFd::new(&stdin, PollFlags::IN)];
+        // Waiting first, as reading stdin can't time out.
+        if left.is_zero() || poll(&mut fds, left.as_millis() as i32).ok()? == 0 {
+            return None;
+        }
+        let mut buffer = [0; 64];
+        let read = rustix::io::read(&stdin, &mut buffer).ok()?;
+        if read == 0 {
+            return None;
+        }
+        reply.extend_from_slice(&buffer[..read]);
+    }
+    Some(String::from_utf8_lossy(&reply).into_owned())
+}
+
+/// Replies can't be read without blocking here, so the sky stays black.
+#[cfg(not(unix))]
+fn read_reply(_timeout: Duration) -> Option<String> {
+    None
+}
+
+/// Parses the color in a reply like `ESC ] 11 ; rgb:1e1e/1e1e/2e2e ESC \`,
+/// whose channels have one to four hex digits each.
+fn parse_reply(reply: &str) -> Option<Color> {
+    let start = reply.find("]11;rgb:")? + "]11;rgb:".len();
+    let rest = &reply[start..];
+    let end = rest.find(['\x1b', '\x07'])?;
+    let channels: Vec<u8> = rest[..end]
+        .split('/')
+        .map(|digits| {
+            if digits.is_empty() || digits.len() > 4 {
+                return None;
+            }
+            let value = u32::from_str_radix(digits, 16).ok()?;
+            let max = (1 << (4 * digits.len())) - 1;
+            Some((value * 255 / max) as u8)
+        })
+        .collect::<Option<_>>()?;
+    match channels[..] {
+        [r, g, b] => Some(Color::from_rgb(r, g, b)),
+        _ => None,
+    }
+}
@@ -0,0 +1,46 @@
+//! Presets bundling several settings into a coherent look.
+//!
+//! A theme is a list of command line flags applied before all other ones, so
+//! any flag given explicitly still overrides what the theme sets.
+
+/// Names of all themes, as accepted by [theme].
+pub const THEME_NAMES: &[&str] = &["night", "dawn", "space"];
+
+/// Flags set by the theme called `name`.
+pub fn theme(name: &str) -> Option<&'static [&'static str]> {
+    let flags: &[&str] = match name {
+        "night" => &[
+            "--background",
+            "#050a1e",
+            "--palette",
+            "cool",
+            "--density",
+            "0.05",
+        ],
+        // The sky lightens into the glow of the rising sun at the horizon.
+        "dawn" => &[
+            "--background",
+            "#1e1030",
+            "--horizon",
+            "#a04830",
+            "--palette",
+            "gold",
+        ],
+        // Willows under more than twice the usual gravity, raining down
+        // quickly.
+        "space" => &[
+            "--background",
+            "#000000",
+            "--palette",
+            "neon",
+            "--kinds",
+            "willow",
+            "--gravity",
+            "0.05",
+            "--trail",
+            "4",
+        ],
+        _ => return None,
+    };
+    Some(flags)
+}
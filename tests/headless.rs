@@ -0,0 +1,36 @@
+use pixel_loop::color::Color;
+use pixel_loop::rand::SeedableRng;
+use pixel_loop::rand_xoshiro::Xoshiro256PlusPlus;
+use terminal_fireworks::{cli, draw_scene, tick, NoopCanvas, SimParams, State};
+
+#[test]
+fn runs_headless() {
+    let args = cli::Args {
+        max_age: 100,
+        max_fireworks: 1000,
+        particles_per_firework: Some(50),
+        ..cli::Args::default()
+    };
+    let mut state = State::new(&args);
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+    let mut canvas = NoopCanvas::new(80, 48);
+    let params = SimParams {
+        spawn_chance: 1.0,
+        ..state.sim_params()
+    };
+    for _ in 0..600 {
+        tick(&mut state, &mut rng, &params);
+        draw_scene(&mut state, &mut canvas);
+        // One launch per step, each gone at most 100 steps later.
+        let stats = state.stats();
+        assert!(stats.fireworks <= 101);
+        assert!(stats.particles <= 50 * stats.fireworks);
+        assert!(stats.dead <= stats.fireworks);
+    }
+    // Warm, the show keeps about a firework in flight for every step of their
+    // age, fewer once some burn out early.
+    let stats = state.stats();
+    assert_eq!((stats.fireworks, stats.dead, stats.particles), (99, 1, 645));
+    let black = Color::from_rgb(0, 0, 0);
+    assert!(canvas.pixels().iter().any(|&pixel| pixel != black));
+}